@@ -1,4 +1,8 @@
-use clap::{Parser, ValueEnum};
+use std::{env, time::Duration};
+
+use clap::{error::ErrorKind, CommandFactory, Parser, ValueEnum};
+
+use crate::TimeThreshold;
 
 /// Command line arguments.
 ///
@@ -42,14 +46,118 @@ pub struct Arguments {
     #[arg(long = "list", help = "List all tests and benchmarks")]
     pub list: bool,
 
-    /// No-op, ignored (libtest-mimic always runs in no-capture mode)
-    #[arg(long = "nocapture", help = "No-op (libtest-mimic always runs in no-capture mode)")]
+    /// Don't capture the output of tests. Without `--isolate`, only the log of
+    /// a [`TestContext`][crate::TestContext] is captured, which is printed
+    /// directly instead. In `--isolate` mode, stdout and stderr of the child
+    /// processes are not captured either.
+    #[arg(
+        long = "nocapture",
+        help = "Don't capture stdout/stderr of tests (these are only captured with --isolate, \n\
+            otherwise this only affects TestContext logs)",
+    )]
     pub nocapture: bool,
 
-    /// No-op, ignored. libtest-mimic does not currently capture stdout.
-    #[arg(long = "show-output")]
+    /// Show captured output of successful tests, too. Only `--isolate` mode
+    /// and [`TestContext`][crate::TestContext] logs capture output.
+    #[arg(long = "show-output", help = "Show captured stdout of successful tests")]
     pub show_output: bool,
 
+    /// Show the execution time of each test in the pretty output. Slow tests
+    /// are highlighted according to their [`TimeThreshold`].
+    #[arg(long = "report-time", help = "Show execution time of each test")]
+    pub report_time: bool,
+
+    /// Let tests that passed, but took longer than their critical
+    /// [`TimeThreshold`], fail. Implies `--report-time`.
+    #[arg(
+        long = "ensure-time",
+        help = "Fail tests that exceed their critical execution time (implies --report-time)",
+    )]
+    pub ensure_time: bool,
+
+    /// Execution time thresholds, either for all trials or only for trials of
+    /// one kind. Thresholds for a kind take precedence, and thresholds set via
+    /// [`Trial::with_time_threshold`][crate::Trial::with_time_threshold]
+    /// take precedence over both. If several apply, the last one wins.
+    #[arg(
+        long = "time-threshold",
+        value_name = "[KIND=]WARN,CRITICAL",
+        value_parser = parse_time_threshold,
+        help = "Set the execution time thresholds (in seconds) for --report-time and \n\
+            --ensure-time, optionally only for tests of the given kind \n\
+            (default: 0.05,0.1; this flag can be used multiple times)",
+    )]
+    pub time_thresholds: Vec<TimeThresholdSetting>,
+
+    /// If set, every test and benchmark is executed in its own child process
+    /// (by re-executing the current binary with `--exact <name>`). That way,
+    /// aborts, crashes and calls to `process::exit` only affect a single
+    /// trial, which is then reported as failed. Unless `--nocapture` is set,
+    /// stdout and stderr of each child process are captured and shown for
+    /// failed trials.
+    ///
+    /// This requires that your `main` creates the same trials on every
+    /// invocation and passes the CLI arguments (e.g. via
+    /// [`Arguments::from_args`]) to [`run`][crate::run].
+    #[arg(long = "isolate", help = "Run each test in its own child process")]
+    pub isolate: bool,
+
+    /// Set by the parent process when running with `--isolate`. Not meant to
+    /// be set manually.
+    #[doc(hidden)]
+    #[arg(long = "isolated-child", hide = true)]
+    pub isolated_child: bool,
+
+    /// Run trials in random order. The seed is printed at the start of the
+    /// run, so that the order can be reproduced with `--shuffle-seed`. Also
+    /// enabled by the environment variable `RUST_TEST_SHUFFLE` (only read by
+    /// [`from_args`][Arguments::from_args]).
+    #[arg(long = "shuffle", help = "Run tests in random order")]
+    pub shuffle: bool,
+
+    /// Start the trials that took longest in earlier runs first, so that the
+    /// run is not held up by a slow trial that happens to start last. The
+    /// durations are read from the `--timing-history` file, which is
+    /// required. Only affects parallel runs; takes precedence over
+    /// `--shuffle` (which then only orders trials with the same duration).
+    #[arg(
+        long = "longest-first",
+        requires = "timing_history",
+        help = "Start the tests that took longest in earlier runs first (requires \n\
+            --timing-history)",
+    )]
+    pub longest_first: bool,
+
+    /// Only run the trials that failed (or timed out) in the last run, as
+    /// recorded in the `--failures-file`, which is required. The others are
+    /// counted as filtered out.
+    #[arg(
+        long = "rerun-failed",
+        requires = "failures_file",
+        help = "Only run the tests that failed in the last run (requires --failures-file)",
+    )]
+    pub rerun_failed: bool,
+
+    /// Run the trials that failed (or timed out) in the last run, as recorded
+    /// in the `--failures-file`, which is required, before all others.
+    #[arg(
+        long = "failed-first",
+        requires = "failures_file",
+        help = "Run the tests that failed in the last run first (requires --failures-file)",
+    )]
+    pub failed_first: bool,
+
+    /// Exclude trials that are expected to panic (see
+    /// [`Trial::should_panic`][crate::Trial::should_panic]). They are counted
+    /// as filtered out.
+    #[arg(long = "exclude-should-panic", help = "Exclude tests marked as should_panic")]
+    pub exclude_should_panic: bool,
+
+    /// Stop starting new trials after the first failure. Same as
+    /// `--max-fail 1`.
+    #[arg(long = "fail-fast", help = "Stop running tests after the first failure")]
+    pub fail_fast: bool,
+
     /// No-op, ignored. Flag only exists for CLI compatibility with libtest.
     #[arg(short = 'Z')]
     pub unstable_flags: Option<UnstableFlags>,
@@ -83,6 +191,96 @@ pub struct Arguments {
     )]
     pub test_threads: Option<usize>,
 
+    /// Limits how many of the test threads trials of one kind may use at
+    /// once, e.g. `e2e=2`. A trial uses as many threads as its weight (see
+    /// [`Trial::with_weight`][crate::Trial::with_weight]). If several apply
+    /// to the same kind, the last one wins.
+    #[arg(
+        long = "kind-threads",
+        value_name = "KIND=N",
+        value_parser = parse_kind_threads,
+        help = "Let tests of the given kind use at most N test threads at once \n\
+            (this flag can be used multiple times)",
+    )]
+    pub kind_threads: Vec<KindThreadsSetting>,
+
+    /// Run trials in random order determined by the given seed. Implies
+    /// `--shuffle`. Also read from the environment variable
+    /// `RUST_TEST_SHUFFLE_SEED` (only by [`from_args`][Arguments::from_args]).
+    #[arg(
+        long = "shuffle-seed",
+        value_name = "SEED",
+        help = "Run tests in random order, determined by the given seed",
+    )]
+    pub shuffle_seed: Option<u64>,
+
+    /// Only run the given shard of all trials (after filtering). Trials are
+    /// assigned to shards by a stable hash of their kind and name, so adding
+    /// or removing a trial does not move any other trial to another shard.
+    #[arg(
+        long = "shard",
+        value_name = "INDEX/TOTAL",
+        value_parser = parse_shard,
+        help = "Only run the INDEX-th of TOTAL shards of all tests (e.g. 2/8, INDEX \n\
+            starts at 1), for splitting tests across several machines",
+    )]
+    pub shard: Option<Shard>,
+
+    /// Stop starting new trials once this many trials have failed (or timed
+    /// out). Trials that are already running are still finished; the others
+    /// are counted in [`Conclusion::num_not_run`][crate::Conclusion::num_not_run].
+    #[arg(
+        long = "max-fail",
+        value_name = "N",
+        value_parser = clap::value_parser!(u64).range(1..),
+        help = "Stop running tests after N failures",
+    )]
+    pub max_fail: Option<u64>,
+
+    /// Default number of retries for failed trials that do not specify their
+    /// own via [`Trial::with_retries`][crate::Trial::with_retries].
+    #[arg(
+        long = "retries",
+        value_name = "N",
+        help = "Retry failed tests up to N times; tests that pass on a retry are \n\
+            reported as flaky",
+    )]
+    pub retries: Option<u32>,
+
+    /// Default timeout for all tests and benchmarks that do not specify their
+    /// own via [`Trial::with_timeout`][crate::Trial::with_timeout].
+    #[arg(
+        long = "timeout",
+        value_name = "SECONDS",
+        value_parser = parse_seconds,
+        help = "Fail tests that do not finish within SECONDS (fractions are allowed)",
+    )]
+    pub timeout: Option<Duration>,
+
+    /// Path of a file recording how long each trial took, e.g. somewhere in
+    /// `target/`. It is updated at the end of each run, keeping the durations
+    /// of trials that were not run. If that fails, a warning is printed to
+    /// stderr. See `--longest-first`.
+    #[arg(
+        long = "timing-history",
+        value_name = "PATH",
+        help = "Record the execution time of each test in the file at PATH",
+    )]
+    pub timing_history: Option<String>,
+
+    /// Path of a file recording which trials failed, e.g. somewhere in
+    /// `target/`. It lists their names like `--list` does. It is updated at
+    /// the end of each run: trials that passed are removed, trials that failed
+    /// are added and trials that were not run are kept. If that fails, a
+    /// warning is printed to stderr. See `--rerun-failed` and
+    /// `--failed-first`.
+    #[arg(
+        long = "failures-file",
+        value_name = "PATH",
+        help = "Record the names of failed tests in the file at PATH",
+    )]
+    pub failures_file: Option<String>,
+
     /// Path of the logfile. If specified, everything will be written into the
     /// file instead of stdout.
     #[arg(
@@ -117,14 +315,27 @@ pub struct Arguments {
     #[arg(
         long = "format",
         value_enum,
-        value_name = "pretty|terse|json",
+        value_name = "pretty|terse|json|junit|tap",
         help = "Configure formatting of output: \n\
             - pretty = Print verbose output\n\
             - terse = Display one character per test\n\
-            - json = Print json events\n",
+            - json = Print json events\n\
+            - junit = Print a JUnit XML report\n\
+            - tap = Print Test Anything Protocol (TAP) output\n",
     )]
     pub format: Option<FormatSetting>,
 
+    /// Additional reports that are written to files, each with its own format.
+    /// These are produced in addition to the normal output.
+    #[arg(
+        long = "report",
+        value_name = "FORMAT=PATH",
+        value_parser = parse_report,
+        help = "Additionally write a report in FORMAT (pretty|terse|json|junit|tap) \n\
+            to the file at PATH (this flag can be used multiple times)",
+    )]
+    pub report: Vec<ReportSetting>,
+
     // ============== POSITIONAL VALUES =======================================
     /// Filter string. Only tests which contain this string are run.
     #[arg(
@@ -141,12 +352,30 @@ impl Arguments {
     /// If the parsing fails (due to incorrect CLI args), an error is shown and
     /// the application exits. If help is requested (`-h` or `--help`), a help
     /// message is shown and the application exits, too.
+    ///
+    /// Unlike [`from_iter`][Arguments::from_iter], this also honors the
+    /// environment variables `RUST_TEST_SHUFFLE` and `RUST_TEST_SHUFFLE_SEED`.
     pub fn from_args() -> Self {
-        Parser::parse()
+        let mut args: Self = Parser::parse();
+        if env::var_os("RUST_TEST_SHUFFLE").is_some() {
+            args.shuffle = true;
+        }
+        if args.shuffle_seed.is_none() {
+            if let Some(seed) = env::var_os("RUST_TEST_SHUFFLE_SEED") {
+                let seed = seed.to_str().and_then(|s| s.parse().ok()).unwrap_or_else(|| {
+                    let msg = format!("RUST_TEST_SHUFFLE_SEED is {seed:?}, should be a number");
+                    Self::command().error(ErrorKind::ValueValidation, msg).exit()
+                });
+                args.shuffle_seed = Some(seed);
+            }
+        }
+
+        args
     }
 
     /// Like `from_args()`, but operates on an explicit iterator and not the
     /// global arguments. Note that the first element is the executable name!
+    #[allow(clippy::should_implement_trait)]
     pub fn from_iter<I>(iter: I) -> Self
     where
         Self: Sized,
@@ -157,11 +386,75 @@ impl Arguments {
     }
 }
 
+/// Parses a non-negative number of seconds (e.g. `1.5`) into a `Duration`.
+fn parse_seconds(s: &str) -> Result<Duration, String> {
+    let secs: f64 = s.parse().map_err(|e| format!("{e}"))?;
+    if !secs.is_finite() || secs < 0.0 || secs > u64::MAX as f64 {
+        return Err(format!("'{s}' is not a valid number of seconds"));
+    }
+
+    Ok(Duration::from_secs_f64(secs))
+}
+
+/// Parses a `[KIND=]WARN,CRITICAL` value of `--time-threshold`.
+fn parse_time_threshold(s: &str) -> Result<TimeThresholdSetting, String> {
+    let (kind, thresholds) = match s.split_once('=') {
+        Some((kind, thresholds)) => (Some(kind.to_owned()), thresholds),
+        None => (None, s),
+    };
+    let (warn, critical) = thresholds.split_once(',')
+        .ok_or_else(|| format!("'{s}' is not of the form [KIND=]WARN,CRITICAL"))?;
+    let threshold = TimeThreshold::new(parse_seconds(warn)?, parse_seconds(critical)?);
+    if threshold.warn > threshold.critical {
+        return Err(format!("warn threshold is larger than critical threshold in '{s}'"));
+    }
+
+    Ok(TimeThresholdSetting { kind, threshold })
+}
+
+/// Parses a `KIND=N` value of `--kind-threads`.
+fn parse_kind_threads(s: &str) -> Result<KindThreadsSetting, String> {
+    let (kind, threads) = s.split_once('=')
+        .ok_or_else(|| format!("'{s}' is not of the form KIND=N"))?;
+    let threads: usize = threads.parse().map_err(|e| format!("invalid number of threads: {e}"))?;
+    if threads == 0 {
+        return Err(format!("number of threads must be at least 1 in '{s}'"));
+    }
+
+    Ok(KindThreadsSetting { kind: kind.to_owned(), threads })
+}
+
+/// Parses an `INDEX/TOTAL` value of `--shard`.
+fn parse_shard(s: &str) -> Result<Shard, String> {
+    let (index, total) = s.split_once('/')
+        .ok_or_else(|| format!("'{s}' is not of the form INDEX/TOTAL"))?;
+    let index: u64 = index.parse().map_err(|e| format!("invalid shard index: {e}"))?;
+    let total: u64 = total.parse().map_err(|e| format!("invalid number of shards: {e}"))?;
+    if index == 0 || index > total {
+        return Err(format!("shard index must be between 1 and {total}, but is {index}"));
+    }
+
+    Ok(Shard::new(index, total))
+}
+
+/// Parses a `FORMAT=PATH` value of `--report`.
+fn parse_report(s: &str) -> Result<ReportSetting, String> {
+    let (format, path) = s.split_once('=')
+        .ok_or_else(|| format!("'{s}' is not of the form FORMAT=PATH"))?;
+    let format = FormatSetting::from_str(format, true)?;
+    if path.is_empty() {
+        return Err(format!("no path given in '{s}'"));
+    }
+
+    Ok(ReportSetting { format, path: path.to_owned() })
+}
+
 /// Possible values for the `--color` option.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
 pub enum ColorSetting {
     /// Colorize output if stdout is a tty and tests are run on serially
     /// (default).
+    #[default]
     Auto,
 
     /// Always colorize output.
@@ -171,12 +464,6 @@ pub enum ColorSetting {
     Never,
 }
 
-impl Default for ColorSetting {
-    fn default() -> Self {
-        ColorSetting::Auto
-    }
-}
-
 /// Possible values for the `-Z` option
 #[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
 pub enum UnstableFlags {
@@ -184,9 +471,10 @@ pub enum UnstableFlags {
 }
 
 /// Possible values for the `--format` option.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
 pub enum FormatSetting {
     /// One line per test. Output for humans. (default)
+    #[default]
     Pretty,
 
     /// One character per test. Usefull for test suites with many tests.
@@ -194,11 +482,75 @@ pub enum FormatSetting {
 
     /// Json output
     Json,
+
+    /// JUnit XML report, printed at the end of the run.
+    Junit,
+
+    /// Test Anything Protocol (version 14).
+    Tap,
+}
+
+/// A value of the `--report` option: a file that an additional report is
+/// written to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReportSetting {
+    /// Format of the report.
+    pub format: FormatSetting,
+
+    /// Path of the file the report is written to. The file is overwritten if
+    /// it already exists.
+    pub path: String,
 }
 
-impl Default for FormatSetting {
-    fn default() -> Self {
-        FormatSetting::Pretty
+/// A value of the `--time-threshold` option.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimeThresholdSetting {
+    /// Kind of the trials these thresholds apply to, or `None` for all trials.
+    pub kind: Option<String>,
+
+    /// The thresholds.
+    pub threshold: TimeThreshold,
+}
+
+/// A value of the `--kind-threads` option.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KindThreadsSetting {
+    /// Kind of the trials this limit applies to.
+    pub kind: String,
+
+    /// Number of test threads the trials of this kind may use at once.
+    pub threads: usize,
+}
+
+/// A value of the `--shard` option: the `index`-th of `total` shards.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Shard {
+    index: u64,
+    total: u64,
+}
+
+impl Shard {
+    /// Creates the `index`-th of `total` shards, starting at 1.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is 0 or greater than `total`.
+    pub fn new(index: u64, total: u64) -> Self {
+        assert!(
+            index > 0 && index <= total,
+            "shard index must be between 1 and {total}, but is {index}",
+        );
+        Self { index, total }
+    }
+
+    /// Index of the shard, starting at 1.
+    pub fn index(&self) -> u64 {
+        self.index
+    }
+
+    /// Total number of shards.
+    pub fn total(&self) -> u64 {
+        self.total
     }
 }
 
@@ -208,7 +560,6 @@ mod tests {
 
     #[test]
     fn verify_cli() {
-        use clap::CommandFactory;
         Arguments::command().debug_assert();
     }
 }
@@ -0,0 +1,158 @@
+//! The per-trial context of [`Trial::test_with_context`][crate::Trial::test_with_context].
+
+use std::{
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    process,
+    sync::{atomic::{AtomicU64, Ordering}, Arc, Mutex},
+};
+
+use crate::{printer, shuffle, Outcome, RunEnv, TestInfo};
+
+
+/// Provided by the harness to runners of [`Trial::test_with_context`][tc].
+///
+/// [tc]: crate::Trial::test_with_context
+#[derive(Debug)]
+pub struct TestContext {
+    name: String,
+    kind: String,
+    seed: u64,
+    temp_dir: PathBuf,
+    attempt: Arc<Attempt>,
+}
+
+impl TestContext {
+    /// Creates the context for the trial currently run in `env`, including
+    /// its temporary directory.
+    pub(crate) fn new(env: &RunEnv) -> io::Result<Self> {
+        let attempt = env.attempt.clone();
+        let temp_dir = create_temp_dir(&attempt.name)?;
+        *attempt.temp_dir.lock().unwrap() = Some(temp_dir.clone());
+
+        let name_with_kind = match attempt.kind.as_str() {
+            "" => attempt.name.clone(),
+            kind => format!("[{kind}] {}", attempt.name),
+        };
+        let run_seed = env.shuffle_seed.unwrap_or(0);
+        let seed = shuffle::Rng::new(run_seed ^ shuffle::hash(&name_with_kind)).next_u64();
+
+        Ok(Self {
+            name: attempt.name.clone(),
+            kind: attempt.kind.clone(),
+            seed,
+            temp_dir,
+            attempt,
+        })
+    }
+
+    /// Returns the name of the trial.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the kind of the trial. If no kind was set, this is an empty
+    /// string.
+    pub fn kind(&self) -> &str {
+        &self.kind
+    }
+
+    /// Returns a seed for random number generators. It only depends on the
+    /// trial's name and kind and the seed of the run, which is the shuffle
+    /// seed (see `--shuffle-seed`) or 0 if trials are not shuffled. So a
+    /// failure can be reproduced by running with the same shuffle seed.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Returns a directory private to this trial (or rather, to this attempt
+    /// of it). It is removed if the trial passes and kept otherwise, in which
+    /// case its path is shown as a note of the failure.
+    pub fn temp_dir(&self) -> &Path {
+        &self.temp_dir
+    }
+
+    /// Writes a line to the trial's log. The log ends up in the captured
+    /// output of the trial, which is shown for failures (and with
+    /// `--show-output` for successes). With `--nocapture`, it is printed to
+    /// stdout immediately.
+    pub fn log(&self, msg: impl fmt::Display) {
+        match &self.attempt.log {
+            Some(log) => {
+                use fmt::Write;
+                writeln!(log.lock().unwrap(), "{}", msg).unwrap();
+            }
+            None => println!("{}", msg),
+        }
+    }
+}
+
+/// State of a single attempt to run a trial, shared between the harness and
+/// the [`TestContext`] (if the trial uses one).
+#[derive(Debug, Default)]
+pub(crate) struct Attempt {
+    name: String,
+    kind: String,
+
+    /// Log of the `TestContext`, or `None` if it is printed directly.
+    log: Option<Mutex<String>>,
+
+    temp_dir: Mutex<Option<PathBuf>>,
+}
+
+impl Attempt {
+    pub(crate) fn new(info: &TestInfo, capture: bool) -> Self {
+        Self {
+            name: info.name.clone(),
+            kind: info.kind.clone(),
+            log: capture.then(Default::default),
+            temp_dir: Mutex::new(None),
+        }
+    }
+
+    /// Cleans up after the attempt finished with `outcome`: removes the
+    /// temporary directory if the trial did not fail, or adds its path to
+    /// the failure otherwise. Returns the final outcome and the captured log,
+    /// if anything was logged.
+    pub(crate) fn finish(&self, outcome: Outcome) -> (Outcome, Option<String>) {
+        let temp_dir = self.temp_dir.lock().unwrap().take();
+        let outcome = match (temp_dir, outcome) {
+            (Some(dir), Outcome::Failed(failed)) => {
+                let note = format!("temporary directory kept at {}", dir.display());
+                Outcome::Failed(failed.with_note(note))
+            }
+            (Some(dir), outcome) if !printer::is_failure(&outcome) => {
+                let _ = fs::remove_dir_all(dir);
+                outcome
+            }
+            (_, outcome) => outcome,
+        };
+
+        let log = self.log.as_ref()
+            .map(|log| std::mem::take(&mut *log.lock().unwrap()))
+            .filter(|log| !log.is_empty());
+        (outcome, log)
+    }
+}
+
+/// Creates a new, empty directory in the system's temporary directory. Its
+/// name contains the trial name to make it easy to find.
+fn create_temp_dir(name: &str) -> io::Result<PathBuf> {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    let name = name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '_' })
+        .take(50)
+        .collect::<String>();
+    loop {
+        // The directory might be left over from an earlier process with the
+        // same ID, so we simply try the next one.
+        let n = COUNTER.fetch_add(1, Ordering::Relaxed);
+        let dir = std::env::temp_dir()
+            .join(format!("libtest-mimic-{}-{}-{}", name, process::id(), n));
+        match fs::create_dir(&dir) {
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            result => return result.map(|()| dir),
+        }
+    }
+}
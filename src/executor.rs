@@ -0,0 +1,67 @@
+//! Running async trials (see [`Trial::test_async`][crate::Trial::test_async]).
+
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll, Wake, Waker},
+    thread::{self, Thread},
+};
+
+use crate::Failed;
+
+
+/// The future of an async trial, as passed to [`Executor::block_on`].
+pub type TrialFuture = Pin<Box<dyn Future<Output = Result<(), Failed>> + Send>>;
+
+/// Drives the futures of async trials to completion, e.g. on a shared Tokio
+/// runtime. Set via [`RunOptions::with_executor`][crate::RunOptions::with_executor].
+///
+/// Each async trial is still run on one of the harness' worker threads, which
+/// blocks until the future has completed. So `--test-threads` and timeouts
+/// apply to async trials just like to all others.
+///
+/// ```
+/// use libtest_mimic::{Executor, Failed, TrialFuture};
+///
+/// struct MyExecutor;
+///
+/// impl Executor for MyExecutor {
+///     fn block_on(&self, future: TrialFuture) -> Result<(), Failed> {
+///         // With Tokio, this would be `self.runtime.block_on(future)`.
+///         # let _ = future; Ok(())
+///     }
+/// }
+/// ```
+pub trait Executor: Send + Sync {
+    /// Runs `future` to completion, blocking the current thread. This is
+    /// called concurrently from multiple worker threads.
+    fn block_on(&self, future: TrialFuture) -> Result<(), Failed>;
+}
+
+/// The executor used if none is configured: polls the future on the current
+/// thread and parks the thread while the future is pending. This is enough
+/// for futures that do not depend on a specific runtime.
+pub(crate) struct BlockOn;
+
+impl Executor for BlockOn {
+    fn block_on(&self, mut future: TrialFuture) -> Result<(), Failed> {
+        let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+        let mut cx = Context::from_waker(&waker);
+        loop {
+            match future.as_mut().poll(&mut cx) {
+                Poll::Ready(result) => return result,
+                Poll::Pending => thread::park(),
+            }
+        }
+    }
+}
+
+/// Wakes a thread blocked in [`BlockOn`] by unparking it.
+struct ThreadWaker(Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
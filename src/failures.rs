@@ -0,0 +1,58 @@
+//! The trials that failed in earlier runs (`--failures-file`), which can be
+//! rerun (`--rerun-failed`) or run first (`--failed-first`).
+//!
+//! The file has one JSON-escaped name (including the kind, like `--list`
+//! prints it) per line.
+
+use std::{collections::BTreeSet, fs, io, path::Path};
+
+use crate::{history, TestInfo, Trial};
+
+
+#[derive(Debug, Default)]
+pub(crate) struct Failures {
+    names: BTreeSet<String>,
+}
+
+impl Failures {
+    /// Loads the failures from `path`. If the file does not exist or cannot
+    /// be read, there are none.
+    pub(crate) fn load(path: &Path) -> Self {
+        let Ok(content) = fs::read_to_string(path) else {
+            return Self::default();
+        };
+
+        let names = content.lines()
+            .filter_map(|line| escape8259::unescape(line).ok())
+            .collect();
+        Self { names }
+    }
+
+    /// Writes the failures to `path`.
+    pub(crate) fn save(&self, path: &Path) -> io::Result<()> {
+        let content = self.names.iter()
+            .map(|name| format!("{}\n", escape8259::escape(name)))
+            .collect::<String>();
+        history::write_atomically(path, &content)
+    }
+
+    pub(crate) fn contains(&self, info: &TestInfo) -> bool {
+        self.names.contains(info.test_name_with_kind().as_ref())
+    }
+
+    /// Records whether the trial with the given name (including the kind)
+    /// failed in this run. Trials that were not run keep their state.
+    pub(crate) fn record(&mut self, name: String, failed: bool) {
+        if failed {
+            self.names.insert(name);
+        } else {
+            self.names.remove(&name);
+        }
+    }
+
+    /// Moves the trials that failed before to the front, keeping the order
+    /// otherwise.
+    pub(crate) fn sort_failed_first(&self, trials: &mut [Trial]) {
+        trials.sort_by_key(|t| !self.contains(&t.info));
+    }
+}
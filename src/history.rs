@@ -0,0 +1,91 @@
+//! Durations of trials in earlier runs (`--timing-history`), which are used
+//! to start the longest trials first (`--longest-first`).
+//!
+//! The history file has one line per trial: its duration in nanoseconds and
+//! its JSON-escaped name (including the kind), separated by a tab. Malformed
+//! lines are ignored, so a broken file only loses some of the history.
+
+use std::{
+    cmp::Reverse,
+    collections::HashMap,
+    fs, io,
+    path::Path,
+    time::Duration,
+};
+
+use crate::Trial;
+
+
+#[derive(Debug, Default)]
+pub(crate) struct History {
+    durations: HashMap<String, Duration>,
+}
+
+impl History {
+    /// Loads the history from `path`. If the file does not exist or cannot be
+    /// read, the history is empty.
+    pub(crate) fn load(path: &Path) -> Self {
+        let Ok(content) = fs::read_to_string(path) else {
+            return Self::default();
+        };
+
+        let durations = content.lines()
+            .filter_map(|line| {
+                let (nanos, name) = line.split_once('\t')?;
+                let duration = Duration::from_nanos(nanos.parse().ok()?);
+                Some((escape8259::unescape(name).ok()?, duration))
+            })
+            .collect();
+        Self { durations }
+    }
+
+    /// Writes the history to `path`.
+    pub(crate) fn save(&self, path: &Path) -> io::Result<()> {
+        let mut entries = self.durations.iter().collect::<Vec<_>>();
+        entries.sort();
+        let content = entries.into_iter()
+            .map(|(name, duration)| {
+                format!("{}\t{}\n", duration.as_nanos(), escape8259::escape(name))
+            })
+            .collect::<String>();
+        write_atomically(path, &content)
+    }
+
+    /// Sets the duration of the trial with the given name (including the
+    /// kind), replacing the one from earlier runs.
+    pub(crate) fn record(&mut self, name: String, duration: Duration) {
+        self.durations.insert(name, duration);
+    }
+
+    /// Sorts `trials` by descending duration in earlier runs. We know nothing
+    /// about trials without history (e.g. new ones), so they are assumed to
+    /// take as long as the known ones on average. The order of trials with
+    /// the same duration is kept.
+    pub(crate) fn sort_longest_first(&self, trials: &mut [Trial]) {
+        let known = trials.iter()
+            .filter_map(|t| self.durations.get(t.info.test_name_with_kind().as_ref()))
+            .collect::<Vec<_>>();
+        if known.is_empty() {
+            return;
+        }
+        let average = known.iter().copied().sum::<Duration>() / known.len() as u32;
+
+        trials.sort_by_cached_key(|t| {
+            let duration = self.durations.get(t.info.test_name_with_kind().as_ref());
+            Reverse(duration.copied().unwrap_or(average))
+        });
+    }
+}
+
+/// Writes `content` to the file at `path` (creating its directory if needed),
+/// replacing the file at once, so that other processes never see a partially
+/// written one.
+pub(crate) fn write_atomically(path: &Path, content: &str) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(format!(".{}.tmp", std::process::id()));
+    fs::write(&tmp, content)?;
+    fs::rename(&tmp, path)
+}
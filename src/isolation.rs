@@ -0,0 +1,355 @@
+//! Running trials in child processes (`--isolate`).
+//!
+//! In isolation mode, the harness re-executes its own binary once per trial,
+//! passing `--isolated-child --exact -- <name>`. The child process runs just
+//! that one trial and writes its outcome to stdout as a single line, prefixed
+//! by [`MARKER`]. Everything else it prints, before or after that, is captured
+//! (or, with `--nocapture`, forwarded to the parent's stdout). If the child dies before
+//! reporting an outcome (abort, signal, `process::exit`, ...), its exit status
+//! is turned into a failure.
+
+use std::{
+    io::{self, Read, Write},
+    process::{Child, ChildStdout, Command, ExitStatus, Stdio},
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    run_single, Arguments, Conclusion, Failed, Measurement, Outcome, RunEnv, TestInfo, Trial,
+};
+
+/// Separates the output of the trial from the outcome record written by the
+/// child process.
+const MARKER: &[u8] = b"\n\x1elibtest-mimic-outcome\x1e";
+
+/// How often we check whether a child process with a timeout has exited.
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+
+/// Runs the trial described by `info` in a child process and returns its
+/// outcome and captured output (unless `--nocapture` is set). If the child
+/// does not exit within `timeout`, it is killed.
+pub(crate) fn run_in_child(
+    args: &Arguments,
+    info: &TestInfo,
+    shuffle_seed: Option<u64>,
+    timeout: Option<Duration>,
+) -> (Outcome, Option<String>) {
+    let exe = match std::env::current_exe() {
+        Ok(exe) => exe,
+        Err(e) => {
+            let outcome = Outcome::Failed(format!("failed to find test executable: {e}").into());
+            return (outcome, None);
+        }
+    };
+
+    let mut cmd = Command::new(exe);
+    cmd.args(["--isolated-child", "--exact", "--include-ignored", "--test-threads", "1"]);
+    if args.bench {
+        cmd.arg("--bench");
+    }
+    if let Some(seed) = shuffle_seed {
+        // The seeds of `TestContext`s are derived from it.
+        cmd.arg("--shuffle-seed").arg(seed.to_string());
+    }
+    cmd.arg("--").arg(info.test_name_with_kind().as_ref());
+    let capture = !args.nocapture;
+    cmd.stdin(Stdio::null()).stdout(Stdio::piped());
+    if capture {
+        cmd.stderr(Stdio::piped());
+    }
+
+    let mut child = match cmd.spawn() {
+        Ok(child) => child,
+        Err(e) => {
+            let outcome = Outcome::Failed(format!("failed to spawn test process: {e}").into());
+            return (outcome, None);
+        }
+    };
+
+    // The output has to be read concurrently, as the child would block once
+    // the pipe buffer is full. Captured stdout and stderr end up in the same
+    // buffer, just like with libtest.
+    let captured = CaptureBuffer::default();
+    let stdout = child.stdout.take().expect("stdout of child is piped");
+    let stdout_reader = if capture {
+        let sink = captured.clone();
+        thread::spawn(move || forward_output(stdout, sink))
+    } else {
+        thread::spawn(move || forward_output(stdout, io::stdout()))
+    };
+    let stderr_reader = child.stderr.take().map(|mut stderr| {
+        let mut sink = captured.clone();
+        thread::spawn(move || {
+            let _ = io::copy(&mut stderr, &mut sink);
+        })
+    });
+
+    let status = wait(&mut child, timeout);
+    let record = stdout_reader.join().unwrap_or(None);
+    if let Some(stderr_reader) = stderr_reader {
+        let _ = stderr_reader.join();
+    }
+
+    let stdout = capture.then(|| captured.into_string());
+    let outcome = match status {
+        Err(e) => Outcome::Failed(format!("failed to wait for test process: {e}").into()),
+        Ok(None) => Outcome::TimedOut(timeout.expect("child can only time out with timeout")),
+        Ok(Some(status)) => match record {
+            Some(record) => decode(&record).unwrap_or_else(|| {
+                Outcome::Failed(format!("test process reported invalid outcome '{record}'").into())
+            }),
+            None => Outcome::Failed(describe_exit(status).into()),
+        },
+    };
+
+    (outcome, stdout)
+}
+
+/// Runs the single trial selected by the parent process and reports its
+/// outcome on stdout. Nothing else is printed.
+pub(crate) fn run_as_child(env: &RunEnv, tests: Vec<Trial>) -> Conclusion {
+    let mut conclusion = Conclusion::empty();
+
+    // If the filter did not match anything, we do not report anything either
+    // and the parent will complain.
+    let Some(trial) = tests.into_iter().next() else {
+        return conclusion;
+    };
+
+    // Timeouts are enforced by the parent, which can simply kill us.
+    let (runner, _) = trial.runner.next_attempt();
+    // Logs are printed directly, so they end up in our captured stdout.
+    let env = env.for_attempt(&trial.info, false);
+    let outcome = run_single(runner, env.clone(), trial.info.should_panic, None);
+    let (outcome, _) = env.attempt.finish(outcome);
+    match &outcome {
+        Outcome::Passed => conclusion.num_passed += 1,
+        Outcome::Failed(_) => conclusion.num_failed += 1,
+        Outcome::Ignored(_) => conclusion.num_ignored += 1,
+        Outcome::Measured(_) => conclusion.num_measured += 1,
+        Outcome::TimedOut(_) => conclusion.num_timed_out += 1,
+    }
+
+    let mut out = io::stdout().lock();
+    out.write_all(MARKER).unwrap();
+    writeln!(out, "{}", encode(&outcome)).unwrap();
+    out.flush().unwrap();
+
+    conclusion
+}
+
+/// Waits for the child to exit. Returns `Ok(None)` if it did not exit within
+/// `timeout` and was killed.
+fn wait(child: &mut Child, timeout: Option<Duration>) -> io::Result<Option<ExitStatus>> {
+    let Some(timeout) = timeout else {
+        return child.wait().map(Some);
+    };
+
+    let deadline = Instant::now() + timeout;
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(Some(status));
+        }
+
+        let now = Instant::now();
+        if now >= deadline {
+            child.kill()?;
+            child.wait()?;
+            return Ok(None);
+        }
+        thread::sleep(POLL_INTERVAL.min(deadline - now));
+    }
+}
+
+/// Reads the child's stdout until EOF, writing everything before the marker
+/// to `sink` as soon as possible and everything after the record at the end.
+/// Returns the outcome record following the marker, if any.
+fn forward_output(mut child_out: ChildStdout, mut sink: impl Write) -> Option<String> {
+    let mut pending = Vec::new();
+    let mut chunk = [0; 4096];
+    loop {
+        let n = match child_out.read(&mut chunk) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(_) => break,
+        };
+        pending.extend_from_slice(&chunk[..n]);
+
+        // Once the marker was seen, the rest is handled below.
+        if find(&pending, MARKER).is_some() {
+            continue;
+        }
+
+        // Everything that cannot be the start of the marker can be written
+        // right away.
+        let safe_len = pending.len().saturating_sub(MARKER.len() - 1);
+        let _ = sink.write_all(&pending[..safe_len]);
+        let _ = sink.flush();
+        pending.drain(..safe_len);
+    }
+
+    match find(&pending, MARKER) {
+        Some(pos) => {
+            // The record is a single line. Anything after it was printed by
+            // the harness after `run` returned, so it is treated as output.
+            let rest = &pending[pos + MARKER.len()..];
+            let (record, after) = match rest.iter().position(|&b| b == b'\n') {
+                Some(end) => (&rest[..end], &rest[end + 1..]),
+                None => (rest, &[][..]),
+            };
+            let _ = sink.write_all(&pending[..pos]);
+            let _ = sink.write_all(after);
+            let _ = sink.flush();
+            Some(String::from_utf8_lossy(record).trim_end().to_owned())
+        }
+        None => {
+            let _ = sink.write_all(&pending);
+            let _ = sink.flush();
+            None
+        }
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+/// Serializes an outcome into a single line.
+///
+/// A failure is encoded as `failed`, followed by tab-separated fields, each
+/// consisting of a tag and a fixed number of JSON-escaped strings (which never
+/// contain tabs).
+fn encode(outcome: &Outcome) -> String {
+    match outcome {
+        Outcome::Passed => "passed".into(),
+        Outcome::Failed(failed) => {
+            let mut fields = vec!["failed".to_owned()];
+            let mut push = |tag: &str, values: &[&str]| {
+                fields.push(tag.to_owned());
+                fields.extend(values.iter().map(|v| escape8259::escape(*v)));
+            };
+            if let Some(msg) = &failed.msg {
+                push("msg", &[msg]);
+            }
+            if let Some(expected) = failed.expected() {
+                push("expected", &[expected]);
+            }
+            if let Some(actual) = failed.actual() {
+                push("actual", &[actual]);
+            }
+            if let Some((file, line)) = failed.location() {
+                push("location", &[file, &line.to_string()]);
+            }
+            for note in failed.notes() {
+                push("note", &[note]);
+            }
+            for (key, value) in failed.context() {
+                push("context", &[key, value]);
+            }
+            if let Some(backtrace) = failed.backtrace() {
+                push("backtrace", &[backtrace]);
+            }
+            fields.join("\t")
+        }
+        Outcome::Ignored(None) => "ignored".into(),
+        Outcome::Ignored(Some(reason)) => format!("ignored\t{}", escape8259::escape(reason)),
+        Outcome::Measured(Measurement { avg, variance }) => format!("measured {avg} {variance}"),
+        Outcome::TimedOut(timeout) => format!("timed-out {}", timeout.as_nanos()),
+    }
+}
+
+/// Inverse of [`encode`]. Returns `None` if the record is malformed.
+fn decode(record: &str) -> Option<Outcome> {
+    let (tag, rest) = record.split_once([' ', '\t']).unwrap_or((record, ""));
+    let outcome = match tag {
+        "passed" => Outcome::Passed,
+        "failed" => Outcome::Failed(decode_failed(rest)?),
+        "ignored" if rest.is_empty() => Outcome::Ignored(None),
+        "ignored" => Outcome::Ignored(Some(escape8259::unescape(rest).ok()?)),
+        "measured" => {
+            let (avg, variance) = rest.split_once(' ')?;
+            Outcome::Measured(Measurement {
+                avg: avg.parse().ok()?,
+                variance: variance.parse().ok()?,
+            })
+        }
+        "timed-out" => Outcome::TimedOut(Duration::from_nanos(rest.parse().ok()?)),
+        _ => return None,
+    };
+
+    Some(outcome)
+}
+
+/// Decodes the fields of a `failed` record (see [`encode`]).
+fn decode_failed(fields: &str) -> Option<Failed> {
+    let mut failed = Failed::without_message();
+    let mut fields = fields.split('\t').filter(|f| !f.is_empty());
+    let mut next = || escape8259::unescape(fields.next()?).ok();
+    while let Some(tag) = next() {
+        match tag.as_str() {
+            "msg" => failed.msg = Some(next()?),
+            "expected" => failed = failed.with_expected(next()?),
+            "actual" => failed = failed.with_actual(next()?),
+            "location" => failed = failed.with_location(next()?, next()?.parse().ok()?),
+            "note" => failed = failed.with_note(next()?),
+            "context" => failed = failed.with_context(next()?, next()?),
+            "backtrace" => failed = failed.with_backtrace(next()?),
+            _ => return None,
+        }
+    }
+
+    Some(failed)
+}
+
+/// Describes how a child process ended that did not report an outcome.
+fn describe_exit(status: ExitStatus) -> String {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+
+        if let Some(signal) = status.signal() {
+            let name = match signal {
+                4 => " (SIGILL)",
+                6 => " (SIGABRT)",
+                8 => " (SIGFPE)",
+                9 => " (SIGKILL)",
+                11 => " (SIGSEGV)",
+                15 => " (SIGTERM)",
+                _ => "",
+            };
+            return format!("test process was terminated by signal {signal}{name}");
+        }
+    }
+
+    match status.code() {
+        Some(code) => format!("test process exited with code {code} without reporting an outcome"),
+        None => format!("test process exited without reporting an outcome ({status})"),
+    }
+}
+
+/// Output of a child process, shared between the threads reading its stdout
+/// and stderr.
+#[derive(Clone, Default)]
+struct CaptureBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl CaptureBuffer {
+    fn into_string(self) -> String {
+        let buf = self.0.lock().unwrap();
+        String::from_utf8_lossy(&buf).into_owned()
+    }
+}
+
+impl Write for CaptureBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
@@ -50,6 +50,16 @@
 //! You can then run `cargo test --test mytest` to run it. To see the CLI
 //! arguments supported by this crate, run `cargo test --test mytest -- -h`.
 //!
+//! All output is produced by a [`Reporter`]. [`run`] uses a built-in one
+//! selected by `--format`. To produce your own output instead, implement
+//! [`Reporter`] and call [`run_with_reporter`]. Additional reports can be
+//! written to files with `--report FORMAT=PATH`, e.g. `--report
+//! junit=target/report.xml`, in both cases.
+//!
+//! Async tests can be created with [`Trial::test_async`]. To run them on your
+//! own runtime, implement [`Executor`] and pass it via [`RunOptions`] to
+//! [`run_with_options`].
+//!
 //!
 //! # Known limitations and differences to the official test harness
 //!
@@ -61,11 +71,12 @@
 //!
 //! Some of the notable differences:
 //!
-//! - Output capture and `--nocapture`: simply not supported. The official
+//! - Output capture only works in `--isolate` mode, where each trial runs in
+//!   its own child process whose output can be captured. Otherwise, only
+//!   what is logged via [`TestContext::log`] is captured. The official
 //!   `libtest` uses internal `std` functions to temporarily redirect output.
 //!   `libtest-mimic` cannot use those. See [this issue][capture] for more
 //!   information.
-//! - `--format=junit`
 //! - Also see [#13](https://github.com/LukasKalbertodt/libtest-mimic/issues/13)
 //!
 //! [capture]: https://github.com/LukasKalbertodt/libtest-mimic/issues/9
@@ -73,20 +84,39 @@
 #![forbid(unsafe_code)]
 
 use std::{
+    any::Any,
     borrow::Cow,
+    collections::HashMap,
     fmt,
+    future::Future,
+    path::Path,
     process::{self, ExitCode},
-    sync::{mpsc, Mutex},
+    sync::{atomic::{AtomicU64, Ordering}, mpsc, Arc},
     thread,
-    time::Instant,
+    time::{Duration, Instant},
 };
 
 mod args;
+mod context;
+mod executor;
+mod failures;
+mod history;
+mod isolation;
+mod panic_hook;
 mod printer;
-
-use printer::Printer;
-
-pub use crate::args::{Arguments, ColorSetting, FormatSetting};
+mod reporter;
+mod schedule;
+mod shuffle;
+
+pub use crate::{
+    args::{
+        Arguments, ColorSetting, FormatSetting, KindThreadsSetting, ReportSetting, Shard,
+        TimeThresholdSetting,
+    },
+    context::TestContext,
+    executor::{Executor, TrialFuture},
+    reporter::Reporter,
+};
 
 
 
@@ -98,14 +128,55 @@ pub use crate::args::{Arguments, ColorSetting, FormatSetting};
 /// A trial is created via [`Trial::test`] or [`Trial::bench`]. The trial's
 /// `name` is printed and used for filtering. The `runner` is called when the
 /// test/benchmark is executed to determine its outcome. If `runner` panics,
-/// the trial is considered "failed". If you need the behavior of
-/// `#[should_panic]` you need to catch the panic yourself. You likely want to
-/// compare the panic payload to an expected value anyway.
+/// the trial is considered "failed", unless it is marked via
+/// [`Trial::should_panic`] or [`Trial::should_panic_with`], which behave like
+/// `#[should_panic]`.
+///
+/// Trials created via [`Trial::test_repeatable`] or [`Trial::bench_repeatable`]
+/// can be run multiple times and thus be retried (see
+/// [`Trial::with_retries`]).
 pub struct Trial {
-    runner: Box<dyn FnOnce(bool) -> Outcome + Send>,
+    runner: Runner,
     info: TestInfo,
 }
 
+/// The runner of a trial, which is either called once or can be called
+/// repeatedly (for retries).
+enum Runner {
+    Once(OnceRunner),
+    Repeatable(Arc<dyn Fn(&RunEnv) -> Outcome + Send + Sync>),
+}
+
+/// A runner for a single attempt.
+type OnceRunner = Box<dyn FnOnce(&RunEnv) -> Outcome + Send>;
+
+/// What the harness passes to runners.
+#[derive(Clone)]
+struct RunEnv {
+    /// `false` if benchmarks should actually be measured (`--bench`).
+    test_mode: bool,
+
+    executor: Arc<dyn Executor>,
+
+    /// The seed trials are shuffled with, which is also the seed the seeds of
+    /// [`TestContext`]s are derived from.
+    shuffle_seed: Option<u64>,
+
+    /// The current attempt to run a trial (empty outside of trials).
+    attempt: Arc<context::Attempt>,
+}
+
+impl RunEnv {
+    /// Returns the environment for a new attempt to run the given trial.
+    /// Logs of its [`TestContext`] are captured if `capture` is set.
+    fn for_attempt(&self, info: &TestInfo, capture: bool) -> Self {
+        Self {
+            attempt: Arc::new(context::Attempt::new(info, capture)),
+            ..self.clone()
+        }
+    }
+}
+
 impl Trial {
     /// Creates a (non-benchmark) test with the given name and runner.
     ///
@@ -115,18 +186,65 @@ impl Trial {
     where
         R: FnOnce() -> Result<(), Failed> + Send + 'static,
     {
-        Self {
-            runner: Box::new(move |_test_mode| match runner() {
-                Ok(()) => Outcome::Passed,
-                Err(failed) => Outcome::Failed(failed),
-            }),
-            info: TestInfo {
-                name: name.into(),
-                kind: String::new(),
-                is_ignored: false,
-                is_bench: false,
-            },
-        }
+        let runner = Runner::Once(Box::new(move |_: &RunEnv| test_outcome(runner())));
+        Self::new(name.into(), false, runner)
+    }
+
+    /// Creates an async test with the given name. The future is driven by the
+    /// [`Executor`] set via [`RunOptions::with_executor`] or, if none is set,
+    /// by a minimal built-in executor that simply polls it on the worker
+    /// thread. The latter does not work for futures that need a specific
+    /// runtime (e.g. Tokio's timers or IO).
+    ///
+    /// Like for [`Trial::test`], `Ok(())` means that the test passed.
+    pub fn test_async<F>(name: impl Into<String>, future: F) -> Self
+    where
+        F: Future<Output = Result<(), Failed>> + Send + 'static,
+    {
+        let runner = Runner::Once(Box::new(move |env: &RunEnv| {
+            test_outcome(env.executor.block_on(Box::pin(future)))
+        }));
+        Self::new(name.into(), false, runner)
+    }
+
+    /// Like [`Trial::test`], but the runner receives a [`TestContext`] with
+    /// the trial's name, a temporary directory, a seed for random number
+    /// generators and a log.
+    ///
+    /// ```
+    /// use libtest_mimic::Trial;
+    ///
+    /// let trial = Trial::test_with_context("writes_file", |ctx| {
+    ///     let path = ctx.temp_dir().join("out.txt");
+    ///     ctx.log(format_args!("writing {}", path.display()));
+    ///     std::fs::write(&path, ctx.seed().to_string())?;
+    ///     Ok(())
+    /// });
+    /// ```
+    pub fn test_with_context<R>(name: impl Into<String>, runner: R) -> Self
+    where
+        R: FnOnce(&TestContext) -> Result<(), Failed> + Send + 'static,
+    {
+        let runner = Runner::Once(Box::new(move |env: &RunEnv| {
+            match TestContext::new(env) {
+                Ok(ctx) => test_outcome(runner(&ctx)),
+                Err(e) => {
+                    Outcome::Failed(format!("failed to create temporary directory: {e}").into())
+                }
+            }
+        }));
+        Self::new(name.into(), false, runner)
+    }
+
+    /// Like [`Trial::test`], but the runner can be called multiple times. Only
+    /// such tests are retried when they fail (see [`Trial::with_retries`]),
+    /// unless `--isolate` is set.
+    pub fn test_repeatable<R>(name: impl Into<String>, runner: R) -> Self
+    where
+        R: Fn() -> Result<(), Failed> + Send + Sync + 'static,
+    {
+        let runner = Runner::Repeatable(Arc::new(move |_: &RunEnv| test_outcome(runner())));
+        Self::new(name.into(), false, runner)
     }
 
     /// Creates a benchmark with the given name and runner.
@@ -145,19 +263,40 @@ impl Trial {
     where
         R: FnOnce(bool) -> Result<Option<Measurement>, Failed> + Send + 'static,
     {
+        let runner = Runner::Once(Box::new(move |env: &RunEnv| {
+            bench_outcome(runner(env.test_mode), env.test_mode)
+        }));
+        Self::new(name.into(), true, runner)
+    }
+
+    /// Like [`Trial::bench`], but the runner can be called multiple times. Only
+    /// such benchmarks are retried when they fail (see
+    /// [`Trial::with_retries`]), unless `--isolate` is set.
+    pub fn bench_repeatable<R>(name: impl Into<String>, runner: R) -> Self
+    where
+        R: Fn(bool) -> Result<Option<Measurement>, Failed> + Send + Sync + 'static,
+    {
+        let runner = Runner::Repeatable(Arc::new(move |env: &RunEnv| {
+            bench_outcome(runner(env.test_mode), env.test_mode)
+        }));
+        Self::new(name.into(), true, runner)
+    }
+
+    fn new(name: String, is_bench: bool, runner: Runner) -> Self {
         Self {
-            runner: Box::new(move |test_mode| match runner(test_mode) {
-                Err(failed) => Outcome::Failed(failed),
-                Ok(_) if test_mode => Outcome::Passed,
-                Ok(Some(measurement)) => Outcome::Measured(measurement),
-                Ok(None)
-                    => Outcome::Failed("bench runner returned `Ok(None)` in bench mode".into()),
-            }),
+            runner,
             info: TestInfo {
-                name: name.into(),
+                name,
                 kind: String::new(),
                 is_ignored: false,
-                is_bench: true,
+                ignore_reason: None,
+                is_bench,
+                timeout: None,
+                time_threshold: None,
+                retries: None,
+                should_panic: ShouldPanic::No,
+                resources: Vec::new(),
+                weight: 1,
             },
         }
     }
@@ -193,6 +332,142 @@ impl Trial {
         }
     }
 
+    /// Marks this test as ignored with the given reason, like
+    /// `#[ignore = "reason"]`. The reason is printed instead of just
+    /// `ignored` (e.g. `ignored, needs network`) and listed by `--list`.
+    pub fn with_ignore_reason(self, reason: impl Into<String>) -> Self {
+        Self {
+            info: TestInfo {
+                is_ignored: true,
+                ignore_reason: Some(reason.into()),
+                ..self.info
+            },
+            ..self
+        }
+    }
+
+    /// Sets a timeout for this test/benchmark. If the runner does not finish
+    /// within the given duration, the trial is reported as timed out and the
+    /// run continues without waiting for it. (Default: the `--timeout` CLI
+    /// argument, i.e. [`Arguments::timeout`], or no timeout at all)
+    ///
+    /// Trials with a timeout are executed on their own thread. When the
+    /// timeout expires, that thread is not (and cannot be) stopped; it is
+    /// simply abandoned and keeps running in the background until the
+    /// process exits.
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        Self {
+            info: TestInfo {
+                timeout: Some(timeout),
+                ..self.info
+            },
+            ..self
+        }
+    }
+
+    /// Sets the execution time thresholds for this test/benchmark, overriding
+    /// the ones given via `--time-threshold`. (Default: the thresholds given
+    /// for this trial's kind or for all trials, see
+    /// [`Arguments::time_thresholds`])
+    pub fn with_time_threshold(self, threshold: TimeThreshold) -> Self {
+        Self {
+            info: TestInfo {
+                time_threshold: Some(threshold),
+                ..self.info
+            },
+            ..self
+        }
+    }
+
+    /// Sets how often this test/benchmark is retried if it fails (or times
+    /// out). A trial that passes after a retry is reported as *flaky*.
+    /// (Default: the `--retries` CLI argument, i.e. [`Arguments::retries`],
+    /// or 0)
+    ///
+    /// Retries require a runner that can be called multiple times, so this
+    /// only has an effect for trials created via [`Trial::test_repeatable`] or
+    /// [`Trial::bench_repeatable`]. With `--isolate`, any trial can be retried,
+    /// as each attempt runs in a new child process.
+    pub fn with_retries(self, retries: u32) -> Self {
+        Self {
+            info: TestInfo {
+                retries: Some(retries),
+                ..self.info
+            },
+            ..self
+        }
+    }
+
+    /// Marks this trial as expected to panic, like `#[should_panic]`: it
+    /// passes if the runner panics and fails with "test did not panic as
+    /// expected" if it returns successfully.
+    pub fn should_panic(self) -> Self {
+        Self {
+            info: TestInfo {
+                should_panic: ShouldPanic::Yes,
+                ..self.info
+            },
+            ..self
+        }
+    }
+
+    /// Like [`Trial::should_panic`], but the panic message also has to contain
+    /// `expected`, like `#[should_panic(expected = "...")]`.
+    pub fn should_panic_with(self, expected: impl Into<String>) -> Self {
+        Self {
+            info: TestInfo {
+                should_panic: ShouldPanic::YesWithMessage(expected.into()),
+                ..self.info
+            },
+            ..self
+        }
+    }
+
+    /// Declares that this trial uses the resource with the given name, e.g.
+    /// a fixture on disk or a fixed port. Trials using the same resource are
+    /// never run at the same time, unless a higher limit is set via
+    /// [`RunOptions::with_resource_limit`]. All other trials still run in
+    /// parallel. Can be called multiple times to use multiple resources.
+    ///
+    /// There is one exception: when a trial times out (see
+    /// [`Trial::with_timeout`]), its resources are released right away, so
+    /// that a hanging trial does not block all others using them. Its thread
+    /// cannot be stopped though, so it may still use the resources while the
+    /// next trial runs. With `--isolate`, timed out trials are killed, so
+    /// this cannot happen.
+    pub fn with_resource(mut self, name: impl Into<String>) -> Self {
+        let name = name.into();
+        if !self.info.resources.contains(&name) {
+            self.info.resources.push(name);
+        }
+        self
+    }
+
+    /// Sets how many of the test threads (see `--test-threads`) this trial
+    /// occupies while running, for trials that are heavier than others, e.g.
+    /// because they are multi-threaded themselves. Trials are only started
+    /// while their total weight fits into the number of test threads; a
+    /// trial with a larger weight runs alone. Has no effect if trials run on
+    /// a single thread. (Default: 1)
+    ///
+    /// # Panics
+    ///
+    /// Panics if `weight` is 0.
+    pub fn with_weight(self, weight: usize) -> Self {
+        assert!(weight > 0, "weight of a trial must be at least 1");
+        Self {
+            info: TestInfo { weight, ..self.info },
+            ..self
+        }
+    }
+
+    /// Puts this trial into a serial group: no two trials of the same group
+    /// run at the same time. This is a shorthand for
+    /// [`Trial::with_resource`], with the group being the resource.
+    pub fn serial_group(self, name: impl Into<String>) -> Self {
+        self.with_resource(name)
+    }
+
     /// Returns the name of this trial.
     pub fn name(&self) -> &str {
         &self.info.name
@@ -209,6 +484,43 @@ impl Trial {
         self.info.is_ignored
     }
 
+    /// Returns the reason set via [`Trial::with_ignore_reason`], if any.
+    pub fn ignore_reason(&self) -> Option<&str> {
+        self.info.ignore_reason()
+    }
+
+    /// Returns whether this trial is expected to panic (see
+    /// [`Trial::should_panic`]).
+    pub fn has_should_panic_flag(&self) -> bool {
+        self.info.has_should_panic_flag()
+    }
+
+    /// Returns the timeout set via [`Trial::with_timeout`], if any.
+    pub fn timeout(&self) -> Option<Duration> {
+        self.info.timeout
+    }
+
+    /// Returns the time threshold set via [`Trial::with_time_threshold`], if
+    /// any.
+    pub fn time_threshold(&self) -> Option<TimeThreshold> {
+        self.info.time_threshold
+    }
+
+    /// Returns the number of retries set via [`Trial::with_retries`], if any.
+    pub fn retries(&self) -> Option<u32> {
+        self.info.retries
+    }
+
+    /// Returns the resources set via [`Trial::with_resource`].
+    pub fn resources(&self) -> &[String] {
+        &self.info.resources
+    }
+
+    /// Returns the weight set via [`Trial::with_weight`].
+    pub fn weight(&self) -> usize {
+        self.info.weight
+    }
+
     /// Returns `true` iff this trial is a test (as opposed to a benchmark).
     pub fn is_test(&self) -> bool {
         !self.info.is_bench
@@ -220,6 +532,38 @@ impl Trial {
     }
 }
 
+/// Converts the result of a test runner into an outcome.
+fn test_outcome(result: Result<(), Failed>) -> Outcome {
+    match result {
+        Ok(()) => Outcome::Passed,
+        Err(failed) => failed.into_outcome(),
+    }
+}
+
+/// Converts the result of a benchmark runner into an outcome.
+fn bench_outcome(result: Result<Option<Measurement>, Failed>, test_mode: bool) -> Outcome {
+    match result {
+        Err(failed) => failed.into_outcome(),
+        Ok(_) if test_mode => Outcome::Passed,
+        Ok(Some(measurement)) => Outcome::Measured(measurement),
+        Ok(None) => Outcome::Failed("bench runner returned `Ok(None)` in bench mode".into()),
+    }
+}
+
+impl Runner {
+    /// Returns a runner for a single attempt, plus the runner for further
+    /// attempts if it can be called repeatedly.
+    fn next_attempt(self) -> (OnceRunner, Option<Runner>) {
+        match self {
+            Runner::Once(runner) => (runner, None),
+            Runner::Repeatable(runner) => {
+                let next = Runner::Repeatable(runner.clone());
+                (Box::new(move |env: &RunEnv| runner(env)), Some(next))
+            }
+        }
+    }
+}
+
 impl fmt::Debug for Trial {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         struct OpaqueRunner;
@@ -234,20 +578,125 @@ impl fmt::Debug for Trial {
             .field("name", &self.info.name)
             .field("kind", &self.info.kind)
             .field("is_ignored", &self.info.is_ignored)
+            .field("ignore_reason", &self.info.ignore_reason)
             .field("is_bench", &self.info.is_bench)
+            .field("timeout", &self.info.timeout)
+            .field("time_threshold", &self.info.time_threshold)
+            .field("retries", &self.info.retries)
+            .field("should_panic", &self.info.should_panic)
+            .field("resources", &self.info.resources)
+            .field("weight", &self.info.weight)
             .finish()
     }
 }
 
-#[derive(Debug)]
-struct TestInfo {
+/// Information about a trial (everything except its runner). Passed to
+/// [`Reporter`]s.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestInfo {
     name: String,
     kind: String,
     is_ignored: bool,
+    ignore_reason: Option<String>,
     is_bench: bool,
+    timeout: Option<Duration>,
+    time_threshold: Option<TimeThreshold>,
+    retries: Option<u32>,
+    should_panic: ShouldPanic,
+    resources: Vec<String>,
+    weight: usize,
+}
+
+/// Whether a trial is expected to panic.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ShouldPanic {
+    No,
+    Yes,
+    YesWithMessage(String),
 }
 
 impl TestInfo {
+    /// Returns the name of the trial.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the kind of the trial. If no kind was set, this is an empty
+    /// string.
+    pub fn kind(&self) -> &str {
+        &self.kind
+    }
+
+    /// Returns whether the trial has been marked as *ignored*.
+    pub fn has_ignored_flag(&self) -> bool {
+        self.is_ignored
+    }
+
+    /// Returns the reason set via [`Trial::with_ignore_reason`], if any.
+    pub fn ignore_reason(&self) -> Option<&str> {
+        self.ignore_reason.as_deref()
+    }
+
+    /// Returns whether the trial is expected to panic (see
+    /// [`Trial::should_panic`]).
+    pub fn has_should_panic_flag(&self) -> bool {
+        !matches!(self.should_panic, ShouldPanic::No)
+    }
+
+    /// Returns the resources the trial uses (see [`Trial::with_resource`]).
+    pub fn resources(&self) -> &[String] {
+        &self.resources
+    }
+
+    /// Returns the weight of the trial (see [`Trial::with_weight`]).
+    pub fn weight(&self) -> usize {
+        self.weight
+    }
+
+    /// Returns the string the panic message is expected to contain, if set via
+    /// [`Trial::should_panic_with`].
+    pub fn expected_panic_message(&self) -> Option<&str> {
+        match &self.should_panic {
+            ShouldPanic::YesWithMessage(expected) => Some(expected),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` iff the trial is a benchmark (as opposed to a test).
+    pub fn is_bench(&self) -> bool {
+        self.is_bench
+    }
+
+    /// Returns the timeout set via [`Trial::with_timeout`], if any.
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    /// Returns the time threshold set via [`Trial::with_time_threshold`], if
+    /// any.
+    pub fn time_threshold(&self) -> Option<TimeThreshold> {
+        self.time_threshold
+    }
+
+    /// Returns the number of retries set via [`Trial::with_retries`], if any.
+    pub fn retries(&self) -> Option<u32> {
+        self.retries
+    }
+
+    /// Returns the thresholds that apply to this trial: its own, otherwise the
+    /// last ones given for its kind, otherwise the last ones given for all
+    /// kinds, otherwise the default.
+    fn effective_time_threshold(&self, settings: &[TimeThresholdSetting]) -> TimeThreshold {
+        let for_kind = |kind: Option<&str>| {
+            settings.iter().rev().find(|s| s.kind.as_deref() == kind).map(|s| s.threshold)
+        };
+
+        self.time_threshold
+            .or_else(|| for_kind(Some(&self.kind)))
+            .or_else(|| for_kind(None))
+            .unwrap_or_default()
+    }
+
     fn test_name_with_kind(&self) -> Cow<'_, str> {
         if self.kind.is_empty() {
             Cow::Borrowed(&self.name)
@@ -267,31 +716,215 @@ pub struct Measurement {
     pub variance: u64,
 }
 
-/// Indicates that a test/benchmark has failed. Optionally carries a message.
+/// Execution time limits of a test, used with `--report-time` and
+/// `--ensure-time`.
+///
+/// Tests running at least `warn` long are highlighted in the output. With
+/// `--ensure-time`, tests running at least `critical` long fail. The default
+/// is 50ms/100ms, like libtest's default for unit tests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeThreshold {
+    /// Execution time from which on a test is highlighted as slow.
+    pub warn: Duration,
+
+    /// Execution time from which on a test fails with `--ensure-time`.
+    pub critical: Duration,
+}
+
+impl TimeThreshold {
+    /// Creates thresholds from the two given durations.
+    pub fn new(warn: Duration, critical: Duration) -> Self {
+        Self { warn, critical }
+    }
+
+    fn is_critical(&self, duration: Duration) -> bool {
+        duration >= self.critical
+    }
+
+    fn is_warn(&self, duration: Duration) -> bool {
+        duration >= self.warn
+    }
+}
+
+impl Default for TimeThreshold {
+    fn default() -> Self {
+        Self::new(Duration::from_millis(50), Duration::from_millis(100))
+    }
+}
+
+/// Indicates that a test/benchmark has failed. Optionally carries a message
+/// and structured details.
 ///
 /// You usually want to use the `From` impl of this type, which allows you to
 /// convert any `T: fmt::Display` (e.g. `String`, `&str`, ...) into `Failed`.
-#[derive(Debug, Clone)]
+/// Details can then be added with the `with_*` methods, for example:
+///
+/// ```
+/// use libtest_mimic::Failed;
+///
+/// let failed = Failed::from("output does not match golden file")
+///     .with_expected("foo\nbar\n")
+///     .with_actual("foo\nbaz\n")
+///     .with_location("tests/golden/simple.txt", 2)
+///     .with_context("input", "simple.in");
+/// ```
+///
+/// If both an expected and an actual value are given, a diff of the two is
+/// shown for the failure.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct Failed {
     msg: Option<String>,
+
+    /// Set by [`Failed::skip`]: the trial is ignored instead of failed.
+    skip: bool,
+
+    /// Boxed to keep `Result<(), Failed>` small.
+    details: Option<Box<FailureDetails>>,
+}
+
+/// Structured details of a [`Failed`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct FailureDetails {
+    expected: Option<String>,
+    actual: Option<String>,
+    location: Option<(String, u32)>,
+    notes: Vec<String>,
+    context: Vec<(String, String)>,
+    backtrace: Option<String>,
 }
 
 impl Failed {
     /// Creates an instance without message.
     pub fn without_message() -> Self {
-        Self { msg: None }
+        Self::default()
+    }
+
+    /// Creates an instance that does not mark the trial as failed, but as
+    /// ignored with the given reason. This allows trials to skip themselves
+    /// at runtime, e.g. if a tool they need is not installed:
+    ///
+    /// ```
+    /// use libtest_mimic::{Failed, Trial};
+    ///
+    /// let trial = Trial::test("uses_git", || {
+    ///     if std::process::Command::new("git").arg("--version").output().is_err() {
+    ///         return Err(Failed::skip("git is not installed"));
+    ///     }
+    ///     // ...
+    ///     Ok(())
+    /// });
+    /// ```
+    pub fn skip(reason: impl std::fmt::Display) -> Self {
+        Self {
+            skip: true,
+            ..Self::from(reason)
+        }
+    }
+
+    /// Returns whether this instance was created by [`Failed::skip`].
+    pub fn is_skip(&self) -> bool {
+        self.skip
+    }
+
+    /// Sets the expected value, e.g. the contents of a golden file.
+    pub fn with_expected(mut self, expected: impl Into<String>) -> Self {
+        self.details_mut().expected = Some(expected.into());
+        self
+    }
+
+    /// Sets the actual value, i.e. what was produced instead of the expected
+    /// value.
+    pub fn with_actual(mut self, actual: impl Into<String>) -> Self {
+        self.details_mut().actual = Some(actual.into());
+        self
+    }
+
+    /// Sets the source location of the failure.
+    pub fn with_location(mut self, file: impl Into<String>, line: u32) -> Self {
+        self.details_mut().location = Some((file.into(), line));
+        self
+    }
+
+    /// Sets the location of the caller as the source location of the failure.
+    #[track_caller]
+    pub fn with_caller_location(self) -> Self {
+        let location = std::panic::Location::caller();
+        self.with_location(location.file(), location.line())
+    }
+
+    /// Adds a note that is shown in addition to the message.
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.details_mut().notes.push(note.into());
+        self
+    }
+
+    /// Adds a key/value pair describing the context of the failure, e.g. the
+    /// input file or parameters of a test.
+    pub fn with_context(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.details_mut().context.push((key.into(), value.into()));
+        self
+    }
+
+    pub(crate) fn with_backtrace(mut self, backtrace: impl Into<String>) -> Self {
+        self.details_mut().backtrace = Some(backtrace.into());
+        self
     }
 
     /// Returns the message of this instance.
     pub fn message(&self) -> Option<&str> {
         self.msg.as_deref()
     }
+
+    /// Returns the expected value, if any.
+    pub fn expected(&self) -> Option<&str> {
+        self.details.as_ref()?.expected.as_deref()
+    }
+
+    /// Returns the actual value, if any.
+    pub fn actual(&self) -> Option<&str> {
+        self.details.as_ref()?.actual.as_deref()
+    }
+
+    /// Returns the source location (file and line) of the failure, if any.
+    pub fn location(&self) -> Option<(&str, u32)> {
+        let (file, line) = self.details.as_ref()?.location.as_ref()?;
+        Some((file, *line))
+    }
+
+    /// Returns all notes in the order they were added.
+    pub fn notes(&self) -> &[String] {
+        self.details.as_ref().map_or(&[], |d| &d.notes)
+    }
+
+    /// Returns all context key/value pairs in the order they were added.
+    pub fn context(&self) -> &[(String, String)] {
+        self.details.as_ref().map_or(&[], |d| &d.context)
+    }
+
+    /// Returns the backtrace of the panic that caused this failure. Only set
+    /// for trials that panicked while `RUST_BACKTRACE` was enabled.
+    pub fn backtrace(&self) -> Option<&str> {
+        self.details.as_ref()?.backtrace.as_deref()
+    }
+
+    fn into_outcome(self) -> Outcome {
+        if self.skip {
+            Outcome::Ignored(self.msg)
+        } else {
+            Outcome::Failed(self)
+        }
+    }
+
+    fn details_mut(&mut self) -> &mut FailureDetails {
+        self.details.get_or_insert_with(Default::default)
+    }
 }
 
 impl<M: std::fmt::Display> From<M> for Failed {
     fn from(msg: M) -> Self {
         Self {
-            msg: Some(msg.to_string())
+            msg: Some(msg.to_string()),
+            ..Self::default()
         }
     }
 }
@@ -299,19 +932,52 @@ impl<M: std::fmt::Display> From<M> for Failed {
 
 
 /// The outcome of performing a test/benchmark.
-#[derive(Debug, Clone)]
-enum Outcome {
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Outcome {
     /// The test passed.
     Passed,
 
     /// The test or benchmark failed.
     Failed(Failed),
 
-    /// The test or benchmark was ignored.
-    Ignored,
+    /// The test or benchmark was ignored, optionally with a reason (see
+    /// [`Trial::with_ignore_reason`] and [`Failed::skip`]).
+    Ignored(Option<String>),
 
     /// The benchmark was successfully run.
     Measured(Measurement),
+
+    /// The test or benchmark did not finish within the given timeout.
+    TimedOut(Duration),
+}
+
+/// Everything known about a trial after executing it. Passed to
+/// [`Reporter`]s and contained in the [`Conclusion`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct TrialResult {
+    /// The outcome of the trial.
+    pub outcome: Outcome,
+
+    /// Wall time it took to execute the trial.
+    pub duration: Duration,
+
+    /// Captured output: stdout and stderr in `--isolate` mode and the log of a
+    /// [`TestContext`] otherwise. Nothing is captured with `--nocapture`.
+    pub stdout: Option<String>,
+
+    /// How often the trial was run. This is larger than 1 if the trial was
+    /// retried (see [`Trial::with_retries`]). The other fields describe the
+    /// last attempt, except for `duration`, which includes all attempts.
+    pub attempts: u32,
+}
+
+impl TrialResult {
+    /// Returns `true` if the trial passed, but only after being retried.
+    pub fn is_flaky(&self) -> bool {
+        matches!(self.outcome, Outcome::Passed) && self.attempts > 1
+    }
 }
 
 /// Contains information about the entire test run. Is returned by[`run`].
@@ -320,8 +986,12 @@ enum Outcome {
 /// [`exit()`][Conclusion::exit] on the result of `run` to exit the application
 /// with the correct exit code. But you can also store this value and inspect
 /// its data.
+///
+/// Besides counts, it contains the [`TrialResult`] of every trial, which
+/// e.g. says how long the trial took.
 #[derive(Clone, Debug, PartialEq, Eq)]
 #[must_use = "Call `exit()` or `exit_if_failed()` to set the correct return code"]
+#[non_exhaustive]
 pub struct Conclusion {
     /// Number of tests and benchmarks that were filtered out (either by the
     /// filter-in pattern or by `--skip` arguments).
@@ -338,6 +1008,33 @@ pub struct Conclusion {
 
     /// Number of benchmarks that successfully ran.
     pub num_measured: u64,
+
+    /// Number of tests and benchmarks that did not finish within their
+    /// timeout. These are not counted in `num_failed`, but still make the
+    /// whole run fail.
+    pub num_timed_out: u64,
+
+    /// Number of tests and benchmarks that were not run because they belong to
+    /// a different shard (`--shard`). These are not included in
+    /// `num_filtered_out`.
+    pub num_sharded_out: u64,
+
+    /// Number of tests and benchmarks that were not run because the run was
+    /// stopped early (`--fail-fast` or `--max-fail`) or because the suite
+    /// setup failed.
+    pub num_not_run: u64,
+
+    /// Number of tests and benchmarks that passed, but only after being
+    /// retried. These are not counted in `num_passed`.
+    pub num_flaky: u64,
+
+    /// Whether the suite setup ([`RunOptions::before_all`]) failed. In that
+    /// case, no trial was run and all are counted in `num_not_run`.
+    pub setup_failed: bool,
+
+    /// Info and result of every trial that was run or ignored, in the order
+    /// in which they finished.
+    pub trial_results: Vec<(TestInfo, TrialResult)>,
 }
 
 impl Conclusion {
@@ -353,7 +1050,7 @@ impl Conclusion {
 
     /// Returns whether there have been any failures.
     pub fn has_failed(&self) -> bool {
-        self.num_failed > 0
+        self.num_failed > 0 || self.num_timed_out > 0 || self.setup_failed
     }
 
     /// Exits the application with an appropriate error code (0 if all tests
@@ -382,10 +1079,23 @@ impl Conclusion {
             num_failed: 0,
             num_ignored: 0,
             num_measured: 0,
+            num_timed_out: 0,
+            num_sharded_out: 0,
+            num_not_run: 0,
+            num_flaky: 0,
+            setup_failed: false,
+            trial_results: Vec::new(),
         }
     }
 }
 
+impl Shard {
+    /// Returns whether the given trial belongs to this shard.
+    fn contains(&self, info: &TestInfo) -> bool {
+        shuffle::hash(&info.test_name_with_kind()) % self.total() == self.index() - 1
+    }
+}
+
 impl Arguments {
     /// Returns `true` if the given test should be ignored.
     fn is_ignored(&self, test: &Trial) -> bool {
@@ -394,6 +1104,23 @@ impl Arguments {
             || (!test.info.is_bench && self.bench)
     }
 
+    /// Returns after how many failures no more trials should be started.
+    fn max_fail(&self) -> Option<u64> {
+        match (self.fail_fast, self.max_fail) {
+            (true, _) => Some(1),
+            (false, max_fail) => max_fail,
+        }
+    }
+
+    /// Returns the seed to shuffle the trials with, if they should be shuffled.
+    fn shuffle_seed(&self) -> Option<u64> {
+        match self.shuffle_seed {
+            Some(seed) => Some(seed),
+            None if self.shuffle => Some(shuffle::random_seed()),
+            None => None,
+        }
+    }
+
     fn is_filtered_out(&self, test: &Trial) -> bool {
         let test_name = test.name();
         // Match against the full test name, including the kind. This upholds the invariant that if
@@ -434,6 +1161,10 @@ impl Arguments {
             return true;
         }
 
+        if self.exclude_should_panic && test.info.has_should_panic_flag() {
+            return true;
+        }
+
         false
     }
 }
@@ -446,91 +1177,324 @@ impl Arguments {
 /// The returned value contains a couple of useful information. See
 /// [`Conclusion`] for more information. If `--list` was specified, a list is
 /// printed and a dummy `Conclusion` is returned.
-pub fn run(args: &Arguments, mut tests: Vec<Trial>) -> Conclusion {
+pub fn run(args: &Arguments, tests: Vec<Trial>) -> Conclusion {
+    run_with_options(args, tests, RunOptions::new())
+}
+
+/// Like [`run`], but all output is produced by the given reporter instead of
+/// the built-in one. The `--format`, `--quiet`, `--color` and `--logfile`
+/// arguments are not used then.
+pub fn run_with_reporter(
+    args: &Arguments,
+    tests: Vec<Trial>,
+    reporter: impl Reporter,
+) -> Conclusion {
+    run_with_options(args, tests, RunOptions::new().with_reporter(reporter))
+}
+
+/// Like [`run`], but with additional configuration that cannot be expressed
+/// as command line arguments.
+pub fn run_with_options(
+    args: &Arguments,
+    tests: Vec<Trial>,
+    options: RunOptions<'_>,
+) -> Conclusion {
+    let RunOptions { reporter, executor, hooks, resource_limits } = options;
+    let env = RunEnv {
+        test_mode: !args.bench,
+        executor: executor.unwrap_or_else(|| Arc::new(executor::BlockOn)),
+        shuffle_seed: args.shuffle_seed(),
+        attempt: Default::default(),
+    };
+    match reporter {
+        Some(reporter) => run_impl(args, tests, &env, hooks, resource_limits, |_| reporter),
+        None => run_impl(args, tests, &env, hooks, resource_limits, |tests| {
+            printer::from_args(args, tests)
+        }),
+    }
+}
+
+/// Configuration of a run besides the [`Arguments`], passed to
+/// [`run_with_options`].
+///
+/// This includes setup and teardown hooks. All of them run in the main
+/// process, also with `--isolate`, and none of them run with `--list`.
+#[derive(Default)]
+pub struct RunOptions<'a> {
+    reporter: Option<Box<dyn Reporter + 'a>>,
+    executor: Option<Arc<dyn Executor>>,
+    hooks: Hooks<'a>,
+    resource_limits: HashMap<String, u32>,
+}
+
+/// A hook that is called for every trial (see [`RunOptions::before_each`]).
+type TrialHook<'a> = Box<dyn Fn(&TestInfo) -> Result<(), Failed> + Send + Sync + 'a>;
+
+/// The setup and teardown hooks of [`RunOptions`].
+#[derive(Default)]
+struct Hooks<'a> {
+    before_all: Option<Box<dyn FnOnce() -> Result<(), Failed> + 'a>>,
+    after_all: Option<Box<dyn FnOnce() + 'a>>,
+    each: TrialHooks<'a>,
+}
+
+/// The hooks that are called for every trial, possibly from multiple threads.
+#[derive(Default)]
+struct TrialHooks<'a> {
+    before: Option<TrialHook<'a>>,
+    after: Option<TrialHook<'a>>,
+}
+
+impl<'a> RunOptions<'a> {
+    /// Creates options that behave like [`run`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Produces all output with the given reporter instead of the built-in
+    /// one, like [`run_with_reporter`].
+    pub fn with_reporter(self, reporter: impl Reporter + 'a) -> Self {
+        Self { reporter: Some(Box::new(reporter)), ..self }
+    }
+
+    /// Runs async trials (see [`Trial::test_async`]) with the given executor.
+    pub fn with_executor(self, executor: impl Executor + 'static) -> Self {
+        Self { executor: Some(Arc::new(executor)), ..self }
+    }
+
+    /// Sets up the suite before any trial runs. If `setup` fails (or panics),
+    /// no trial is run: all are counted as not run and
+    /// [`Conclusion::setup_failed`] is set.
+    pub fn before_all(mut self, setup: impl FnOnce() -> Result<(), Failed> + 'a) -> Self {
+        self.hooks.before_all = Some(Box::new(setup));
+        self
+    }
+
+    /// Tears down the suite after all trials have finished, regardless of
+    /// their outcomes, and also if the run unwinds (e.g. because a custom
+    /// [`Reporter`] panicked). If a [`before_all`][Self::before_all] hook is
+    /// set, this also runs if that failed, to clean up partial setups.
+    pub fn after_all(mut self, teardown: impl FnOnce() + 'a) -> Self {
+        self.hooks.after_all = Some(Box::new(teardown));
+        self
+    }
+
+    /// Called before each attempt of each trial. If it fails (or panics), the
+    /// trial fails with that error without running. As trials run in
+    /// parallel, this may be called from multiple threads at once.
+    pub fn before_each(
+        mut self,
+        setup: impl Fn(&TestInfo) -> Result<(), Failed> + Send + Sync + 'a,
+    ) -> Self {
+        self.hooks.each.before = Some(Box::new(setup));
+        self
+    }
+
+    /// Called after each attempt of each trial whose
+    /// [`before_each`][Self::before_each] hook succeeded, regardless of the
+    /// trial's outcome. If it fails (or panics), a trial that passed fails
+    /// with that error instead.
+    pub fn after_each(
+        mut self,
+        teardown: impl Fn(&TestInfo) -> Result<(), Failed> + Send + Sync + 'a,
+    ) -> Self {
+        self.hooks.each.after = Some(Box::new(teardown));
+        self
+    }
+
+    /// Allows up to `limit` trials using the given resource (see
+    /// [`Trial::with_resource`]) to run at the same time. (Default: 1)
+    ///
+    /// # Panics
+    ///
+    /// Panics if `limit` is 0.
+    pub fn with_resource_limit(mut self, resource: impl Into<String>, limit: u32) -> Self {
+        assert!(limit > 0, "resource limit must be at least 1");
+        self.resource_limits.insert(resource.into(), limit);
+        self
+    }
+}
+
+/// Implementation of [`run_with_options`]. The reporter is created with the
+/// filtered trials.
+fn run_impl<R: Reporter>(
+    args: &Arguments,
+    mut tests: Vec<Trial>,
+    env: &RunEnv,
+    hooks: Hooks<'_>,
+    resource_limits: HashMap<String, u32>,
+    make_reporter: impl FnOnce(&[Trial]) -> R,
+) -> Conclusion {
     let start_instant = Instant::now();
     let mut conclusion = Conclusion::empty();
 
+    // Failures of the last run, for `--rerun-failed` and `--failed-first`.
+    let failures = match &args.failures_file {
+        Some(path) if args.rerun_failed || args.failed_first => {
+            failures::Failures::load(Path::new(path))
+        }
+        _ => failures::Failures::default(),
+    };
+
     // Apply filtering
-    if args.filter.is_some() || !args.skip.is_empty() || args.ignored {
+    let has_filter = args.filter.is_some() || !args.skip.is_empty();
+    if has_filter || args.ignored || args.exclude_should_panic || args.rerun_failed {
         let len_before = tests.len() as u64;
-        tests.retain(|test| !args.is_filtered_out(test));
+        tests.retain(|test| {
+            !args.is_filtered_out(test) && (!args.rerun_failed || failures.contains(&test.info))
+        });
         conclusion.num_filtered_out = len_before - tests.len() as u64;
     }
+
+    // Only keep the trials of our shard. This happens after filtering, so
+    // that the shards are about equally large.
+    if let Some(shard) = &args.shard {
+        let len_before = tests.len() as u64;
+        tests.retain(|test| shard.contains(&test.info));
+        conclusion.num_sharded_out = len_before - tests.len() as u64;
+    }
     let tests = tests;
 
-    // Create printer which is used for all output.
-    let mut printer = printer::Printer::new(args, &tests);
+    // If we were spawned by a parent in `--isolate` mode, just run the one
+    // selected trial and report back to the parent.
+    if args.isolated_child {
+        return isolation::run_as_child(env, tests);
+    }
+
+    // Create reporter which is used for all output.
+    let mut reporter = make_reporter(&tests);
 
     // If `--list` is specified, just print the list and return.
     if args.list {
-        printer.print_list(&tests, args.ignored);
+        let infos = tests.iter().map(|test| &test.info).collect::<Vec<_>>();
+        reporter.list(&infos);
         return Conclusion::empty();
     }
 
+    // Additional reports requested via `--report` receive the same events.
+    let mut reporter = printer::Tee::new(reporter, printer::report_files(args, &tests));
+
+    let num_threads = platform_defaults_to_one_thread()
+        .then_some(1)
+        .or(args.test_threads)
+        .or_else(|| std::thread::available_parallelism().ok().map(Into::into))
+        .unwrap_or(1);
+
+    // Shuffle trials if requested, then move long ones (only useful when
+    // running in parallel) and previously failed ones to the front. Both
+    // execution paths below take the trials in this order. The sorts are
+    // stable, so each only reorders trials that the following ones consider
+    // equal.
+    let shuffle_seed = env.shuffle_seed;
+    let mut tests = tests;
+    if let Some(seed) = shuffle_seed {
+        shuffle::shuffle(&mut tests, seed);
+    }
+    if args.longest_first && num_threads > 1 {
+        if let Some(path) = &args.timing_history {
+            history::History::load(Path::new(path)).sort_longest_first(&mut tests);
+        }
+    }
+    if args.failed_first {
+        failures.sort_failed_first(&mut tests);
+    }
+
     // Print number of tests
-    printer.print_title(tests.len() as u64);
+    reporter.run_started(tests.len() as u64, shuffle_seed);
+
+    // If the suite cannot be set up, we do not run any trial. The suite is
+    // torn down in any case, even if something below panics.
+    let Hooks { before_all, after_all, each } = hooks;
+    let after_all = SuiteTeardown(after_all);
+    if let Some(setup) = before_all {
+        if let Err(failed) = run_hook("setup", setup) {
+            reporter.setup_failed(&failed);
+            after_all.run();
+            conclusion.setup_failed = true;
+            conclusion.num_not_run = tests.len() as u64;
+            reporter.summary(&conclusion, start_instant.elapsed());
+            return conclusion;
+        }
+    }
 
-    let mut failed_tests = Vec::new();
-    let mut handle_outcome = |outcome: Outcome, test: TestInfo, printer: &mut Printer| {
-        printer.print_single_outcome(&test, &outcome);
+    let mut handle_outcome = |test: TestInfo, result: TrialResult, reporter: &mut printer::Tee<R>| {
+        reporter.trial_finished(&test, &result);
 
         // Handle outcome
-        match outcome {
+        match result.outcome {
+            Outcome::Passed if result.is_flaky() => conclusion.num_flaky += 1,
             Outcome::Passed => conclusion.num_passed += 1,
-            Outcome::Failed(failed) => {
-                failed_tests.push((test, failed.msg));
-                conclusion.num_failed += 1;
-            },
-            Outcome::Ignored => conclusion.num_ignored += 1,
+            Outcome::Failed(_) => conclusion.num_failed += 1,
+            Outcome::Ignored(_) => conclusion.num_ignored += 1,
             Outcome::Measured(_) => conclusion.num_measured += 1,
+            Outcome::TimedOut(_) => conclusion.num_timed_out += 1,
         }
+        conclusion.trial_results.push((test, result));
     };
 
     // Execute all tests.
-    let test_mode = !args.bench;
-
-    let num_threads = platform_defaults_to_one_thread()
-        .then_some(1)
-        .or(args.test_threads)
-        .or_else(|| std::thread::available_parallelism().ok().map(Into::into))
-        .unwrap_or(1);
+    // With `--fail-fast`/`--max-fail`, no new trials are started once enough
+    // trials have failed. This is counted separately from `conclusion`, as
+    // the worker threads need to access it.
+    let num_tests = tests.len() as u64;
+    let num_failures = AtomicU64::new(0);
+    let max_fail = args.max_fail();
+    let should_stop = || max_fail.map_or(false, |max| num_failures.load(Ordering::SeqCst) >= max);
+    let run_counted = |trial| {
+        let (test_info, result) = run_trial(args, env, &each, trial);
+        if printer::is_failure(&result.outcome) {
+            num_failures.fetch_add(1, Ordering::SeqCst);
+        }
+        (test_info, result)
+    };
 
     if num_threads == 1 {
         // Run test sequentially in main thread
         for test in tests {
+            if should_stop() {
+                break;
+            }
+
             // Print `test foo    ...`, run the test, then print the outcome in
             // the same line.
-            printer.print_test(&test.info);
-            let outcome = if args.is_ignored(&test) {
-                Outcome::Ignored
-            } else {
-                run_single(test.runner, test_mode)
-            };
-            handle_outcome(outcome, test.info, &mut printer);
+            reporter.trial_started(&test.info);
+            let (test_info, result) = run_counted(test);
+            handle_outcome(test_info, result, &mut reporter);
         }
     } else {
         // Run test in thread pool.
         let (sender, receiver) = mpsc::channel();
 
-        let num_tests = tests.len();
-        // TODO: this should use a mpmc channel, once that's stabilized in std.
-        let iter = Mutex::new(tests.into_iter());
+        // Trials are handed out such that their weights fit into the
+        // threads and resource limits are respected.
+        let kind_threads = args.kind_threads.iter()
+            .map(|setting| (setting.kind.clone(), setting.threads))
+            .collect();
+        let scheduler = schedule::Scheduler::new(tests, num_threads, kind_threads, resource_limits);
         thread::scope(|scope| {
             // Start worker threads
             for _ in 0..num_threads {
-                scope.spawn(|| {
+                let sender = sender.clone();
+                let (scheduler, should_stop) = (&scheduler, &should_stop);
+                let run_counted = &run_counted;
+                scope.spawn(move || {
                     loop {
-                        // Get next test to process from the iterator.
-                        let Some(trial) = iter.lock().unwrap().next() else {
+                        if should_stop() {
                             break;
-                        };
+                        }
 
-                        let payload = if args.is_ignored(&trial) {
-                            (Outcome::Ignored, trial.info)
-                        } else {
-                            let outcome = run_single(trial.runner, test_mode);
-                            (outcome, trial.info)
+                        // Get next test to process, possibly waiting for
+                        // resources.
+                        let Some(trial) = scheduler.next() else {
+                            break;
                         };
+                        if should_stop() {
+                            scheduler.release(&trial.info);
+                            break;
+                        }
+
+                        let payload = run_counted(trial);
+                        scheduler.release(&payload.0);
 
                         // It's fine to ignore the result of sending. If the
                         // receiver has hung up, everything will wind down soon
@@ -540,24 +1504,76 @@ pub fn run(args: &Arguments, mut tests: Vec<Trial>) -> Conclusion {
                 });
             }
 
+            // Once all workers are done and dropped their sender, the loop
+            // below ends.
+            drop(sender);
+
             // Print results of tests that already dinished
-            for (outcome, test_info) in receiver.iter().take(num_tests) {
+            for (test_info, result) in receiver.iter() {
                 // In multithreaded mode, we do only print the start of the line
                 // after the test ran, as otherwise it would lead to terribly
                 // interleaved output.
-                printer.print_test(&test_info);
-                handle_outcome(outcome, test_info, &mut printer);
+                reporter.trial_started(&test_info);
+                handle_outcome(test_info, result, &mut reporter);
             }
         });
 
     }
 
-    // Print failures if there were any, and the final summary.
+    after_all.run();
+
+    // The files are loaded again, in case they were changed by another run
+    // in the meantime. Ignored trials did not run, so they are not recorded.
+    let finished = || conclusion.trial_results.iter()
+        .filter(|(_, result)| !matches!(result.outcome, Outcome::Ignored(_)));
+    if let Some(path) = &args.timing_history {
+        let path = Path::new(path);
+        let mut history = history::History::load(path);
+        for (test, result) in finished() {
+            history.record(test.test_name_with_kind().into_owned(), result.duration);
+        }
+        // The results of the run are more important than the history, so
+        // this is only a warning.
+        if let Err(e) = history.save(path) {
+            eprintln!("warning: failed to write timing history to '{}': {e}", path.display());
+        }
+    }
+    if let Some(path) = &args.failures_file {
+        let path = Path::new(path);
+        let mut failures = failures::Failures::load(path);
+        for (test, result) in finished() {
+            let failed = printer::is_failure(&result.outcome);
+            failures.record(test.test_name_with_kind().into_owned(), failed);
+        }
+        if let Err(e) = failures.save(path) {
+            eprintln!("warning: failed to write failures file '{}': {e}", path.display());
+        }
+    }
+
+    let num_run = conclusion.num_passed + conclusion.num_failed + conclusion.num_ignored
+        + conclusion.num_measured + conclusion.num_timed_out + conclusion.num_flaky;
+    conclusion.num_not_run = num_tests - num_run;
+
+    // Print captured output of successful tests (only with `--show-output`),
+    // failures if there were any, and the final summary.
+    let successful_tests = conclusion.trial_results.iter()
+        .filter(|(_, result)| {
+            args.show_output && matches!(result.outcome, Outcome::Passed | Outcome::Measured(_))
+        })
+        .cloned()
+        .collect::<Vec<_>>();
+    let failed_tests = conclusion.trial_results.iter()
+        .filter(|(_, result)| printer::is_failure(&result.outcome))
+        .cloned()
+        .collect::<Vec<_>>();
+    if successful_tests.iter().any(|(_, result)| result.stdout.is_some()) {
+        reporter.successes(&successful_tests);
+    }
     if !failed_tests.is_empty() {
-        printer.print_failures(&failed_tests);
+        reporter.failures(&failed_tests);
     }
 
-    printer.print_summary(&conclusion, start_instant.elapsed());
+    reporter.summary(&conclusion, start_instant.elapsed());
 
     conclusion
 }
@@ -569,22 +1585,202 @@ fn platform_defaults_to_one_thread() -> bool {
     cfg!(target_family = "wasm")
 }
 
-/// Runs the given runner, catching any panics and treating them as a failed test.
-fn run_single(runner: Box<dyn FnOnce(bool) -> Outcome + Send>, test_mode: bool) -> Outcome {
-    use std::panic::{catch_unwind, AssertUnwindSafe};
+/// Executes the given trial according to `args` (or marks it as ignored).
+fn run_trial(
+    args: &Arguments,
+    env: &RunEnv,
+    hooks: &TrialHooks<'_>,
+    trial: Trial,
+) -> (TestInfo, TrialResult) {
+    let start = Instant::now();
+    if args.is_ignored(&trial) {
+        let result = TrialResult {
+            outcome: Outcome::Ignored(trial.info.ignore_reason.clone()),
+            stdout: None,
+            duration: start.elapsed(),
+            attempts: 0,
+        };
+        return (trial.info, result);
+    }
 
-    catch_unwind(AssertUnwindSafe(move || runner(test_mode))).unwrap_or_else(|e| {
-        // The `panic` information is just an `Any` object representing the
-        // value the panic was invoked with. For most panics (which use
-        // `panic!` like `println!`), this is either `&str` or `String`.
-        let payload = e.downcast_ref::<String>()
-            .map(|s| s.as_str())
-            .or(e.downcast_ref::<&str>().map(|s| *s));
+    let timeout = trial.info.timeout.or(args.timeout);
+    let threshold = trial.info.effective_time_threshold(&args.time_thresholds);
+    let retries = trial.info.retries.or(args.retries).unwrap_or(0);
+    let mut runner = Some(trial.runner);
+    let mut attempts = 0;
+    loop {
+        attempts += 1;
+        let setup = hooks.before.as_ref().map(|setup| run_hook("setup", || setup(&trial.info)));
+        if let Some(Err(failed)) = setup {
+            let result = TrialResult {
+                outcome: Outcome::Failed(failed),
+                stdout: None,
+                duration: start.elapsed(),
+                attempts,
+            };
+            return (trial.info, result);
+        }
 
-        let msg = match payload {
-            Some(payload) => format!("test panicked: {payload}"),
-            None => format!("test panicked"),
+        let attempt_start = Instant::now();
+        let (outcome, stdout) = if args.isolate {
+            isolation::run_in_child(args, &trial.info, env.shuffle_seed, timeout)
+        } else {
+            // `runner` is only `None` here if it is not repeatable, in which
+            // case we never retry.
+            let (once, next) = runner.take()
+                .expect("bug: retrying non-repeatable runner")
+                .next_attempt();
+            runner = next;
+            let env = env.for_attempt(&trial.info, !args.nocapture);
+            let outcome = run_single(once, env.clone(), trial.info.should_panic.clone(), timeout);
+            env.attempt.finish(outcome)
+        };
+        let attempt_duration = attempt_start.elapsed();
+
+        // With `--ensure-time`, a test that passed too slowly fails anyway.
+        let outcome = match outcome {
+            Outcome::Passed if args.ensure_time && threshold.is_critical(attempt_duration) => {
+                Outcome::Failed(format!(
+                    "test took {:.3}s, exceeding the critical time limit of {:.3}s",
+                    attempt_duration.as_secs_f64(),
+                    threshold.critical.as_secs_f64(),
+                ).into())
+            }
+            other => other,
+        };
+        let teardown = hooks.after.as_ref()
+            .map(|teardown| run_hook("teardown", || teardown(&trial.info)));
+        let outcome = match teardown {
+            Some(Err(failed)) if !printer::is_failure(&outcome) => Outcome::Failed(failed),
+            _ => outcome,
         };
-        Outcome::Failed(msg.into())
-    })
+
+        let can_retry = args.isolate || runner.is_some();
+        if !printer::is_failure(&outcome) || attempts > retries || !can_retry {
+            let result = TrialResult { outcome, stdout, duration: start.elapsed(), attempts };
+            return (trial.info, result);
+        }
+    }
+}
+
+/// Runs a setup or teardown hook, turning a panic into a failure.
+fn run_hook(what: &str, hook: impl FnOnce() -> Result<(), Failed>) -> Result<(), Failed> {
+    match panic_hook::catch_unwind(hook) {
+        Ok(result) => result,
+        Err((payload, _)) => match panic_message(&*payload) {
+            Some(msg) => Err(format!("{what} panicked: {msg}").into()),
+            None => Err(format!("{what} panicked").into()),
+        },
+    }
+}
+
+/// The [`RunOptions::after_all`] hook, which is also run if it is dropped
+/// without being run, i.e. while unwinding.
+struct SuiteTeardown<'a>(Option<Box<dyn FnOnce() + 'a>>);
+
+impl SuiteTeardown<'_> {
+    fn run(self) {
+        drop(self);
+    }
+}
+
+impl Drop for SuiteTeardown<'_> {
+    fn drop(&mut self) {
+        if let Some(teardown) = self.0.take() {
+            teardown();
+        }
+    }
+}
+
+/// Runs the given runner, catching any panics and treating them as a failed
+/// test. If a timeout is given, the runner is executed on a separate thread
+/// which is abandoned if it does not finish in time.
+fn run_single(
+    runner: OnceRunner,
+    env: RunEnv,
+    should_panic: ShouldPanic,
+    timeout: Option<Duration>,
+) -> Outcome {
+    // On platforms without thread support, we cannot enforce timeouts.
+    let timeout = match timeout {
+        Some(timeout) if !platform_defaults_to_one_thread() => timeout,
+        _ => return run_catching_panics(runner, &env, &should_panic),
+    };
+
+    let (sender, receiver) = mpsc::channel();
+    let spawn_result = thread::Builder::new().spawn(move || {
+        // If the receiver has hung up, the trial timed out and nobody is
+        // interested in the outcome anymore.
+        let _ = sender.send(run_catching_panics(runner, &env, &should_panic));
+    });
+    if let Err(e) = spawn_result {
+        return Outcome::Failed(format!("failed to spawn thread for test: {e}").into());
+    }
+
+    match receiver.recv_timeout(timeout) {
+        Ok(outcome) => outcome,
+        Err(mpsc::RecvTimeoutError::Timeout) => Outcome::TimedOut(timeout),
+        Err(mpsc::RecvTimeoutError::Disconnected) => {
+            Outcome::Failed("test thread terminated without reporting an outcome".into())
+        }
+    }
+}
+
+/// Returns the message of a panic, if it has one.
+fn panic_message(payload: &(dyn Any + Send)) -> Option<&str> {
+    // The `panic` information is just an `Any` object representing the
+    // value the panic was invoked with. For most panics (which use
+    // `panic!` like `println!`), this is either `&str` or `String`.
+    payload.downcast_ref::<String>()
+        .map(|s| s.as_str())
+        .or(payload.downcast_ref::<&str>().copied())
+}
+
+/// Runs the given runner in the current thread, catching any panics and
+/// treating them as a failed test (or as passed test, if a panic is expected).
+fn run_catching_panics(
+    runner: OnceRunner,
+    env: &RunEnv,
+    should_panic: &ShouldPanic,
+) -> Outcome {
+    let (e, details) = match panic_hook::catch_unwind(move || runner(env)) {
+        Ok(Outcome::Passed) if !matches!(should_panic, ShouldPanic::No) => {
+            return Outcome::Failed("test did not panic as expected".into());
+        }
+        Ok(outcome) => return outcome,
+        Err(e) => e,
+    };
+
+    let payload = panic_message(&*e);
+
+    // The messages are the same as libtest's.
+    let msg = match (should_panic, payload) {
+        (ShouldPanic::No, Some(payload)) => format!("test panicked: {payload}"),
+        (ShouldPanic::No, None) => "test panicked".to_string(),
+        (ShouldPanic::Yes, _) => return Outcome::Passed,
+        (ShouldPanic::YesWithMessage(expected), Some(payload)) => {
+            if payload.contains(expected.as_str()) {
+                return Outcome::Passed;
+            }
+            format!(
+                "panic did not contain expected string\n      \
+                    panic message: {payload:?}\n \
+                    expected substring: {expected:?}"
+            )
+        }
+        (ShouldPanic::YesWithMessage(expected), None) => format!(
+            "expected panic with string value,\n \
+                found non-string value\n     \
+                expected substring: {expected:?}"
+        ),
+    };
+
+    let mut failed = Failed::from(msg);
+    if let Some((file, line)) = details.location {
+        failed = failed.with_location(file, line);
+    }
+    if let Some(backtrace) = details.backtrace {
+        failed = failed.with_backtrace(backtrace);
+    }
+    Outcome::Failed(failed)
 }
@@ -0,0 +1,69 @@
+//! Recording where a trial panicked.
+//!
+//! The payload caught by `catch_unwind` does not say where the panic
+//! happened. So we install a panic hook (once, wrapping whatever hook was set
+//! before) that records the location and, if enabled via `RUST_BACKTRACE`, a
+//! backtrace, but only on threads that are currently inside [`catch_unwind`].
+
+use std::{
+    any::Any,
+    backtrace::{Backtrace, BacktraceStatus},
+    cell::{Cell, RefCell},
+    panic::{self, AssertUnwindSafe},
+    sync::Once,
+};
+
+
+thread_local! {
+    /// Whether panics on this thread should be recorded.
+    static RECORDING: Cell<bool> = const { Cell::new(false) };
+
+    /// Details about the last panic on this thread while recording.
+    static LAST_PANIC: RefCell<Option<PanicDetails>> = const { RefCell::new(None) };
+}
+
+/// What the panic hook recorded about a panic.
+#[derive(Debug, Default)]
+pub(crate) struct PanicDetails {
+    pub(crate) location: Option<(String, u32)>,
+    pub(crate) backtrace: Option<String>,
+}
+
+/// Like `std::panic::catch_unwind`, but additionally returns the details of
+/// the panic, if any.
+pub(crate) fn catch_unwind<R>(
+    f: impl FnOnce() -> R,
+) -> Result<R, (Box<dyn Any + Send>, PanicDetails)> {
+    install_hook();
+
+    // Panics caught by the closure itself in an earlier call leave their
+    // details behind, which must not be attributed to a later panic.
+    LAST_PANIC.with(|p| *p.borrow_mut() = None);
+    let was_recording = RECORDING.with(|r| r.replace(true));
+    let result = panic::catch_unwind(AssertUnwindSafe(f));
+    RECORDING.with(|r| r.set(was_recording));
+
+    result.map_err(|payload| {
+        let details = LAST_PANIC.with(|p| p.borrow_mut().take()).unwrap_or_default();
+        (payload, details)
+    })
+}
+
+fn install_hook() {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            if RECORDING.with(|r| r.get()) {
+                let backtrace = Backtrace::capture();
+                let details = PanicDetails {
+                    location: info.location().map(|l| (l.file().to_owned(), l.line())),
+                    backtrace: (backtrace.status() == BacktraceStatus::Captured)
+                        .then(|| backtrace.to_string()),
+                };
+                LAST_PANIC.with(|p| *p.borrow_mut() = Some(details));
+            }
+            previous(info);
+        }));
+    });
+}
@@ -0,0 +1,168 @@
+//! Line-based unified diff of expected and actual values of a failure.
+
+use std::io::Write;
+
+use anstyle::{AnsiColor, Color, Style};
+
+
+/// Number of unchanged lines shown around each change.
+const CONTEXT: usize = 3;
+
+/// Above this many cells of the LCS table (after stripping common prefix and
+/// suffix), we do not compute a minimal diff but simply show all lines as
+/// removed and added.
+const MAX_TABLE_SIZE: usize = 4_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Line<'a> {
+    Same(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+impl Line<'_> {
+    fn is_added(&self) -> bool {
+        matches!(self, Line::Added(_))
+    }
+
+    fn is_removed(&self) -> bool {
+        matches!(self, Line::Removed(_))
+    }
+}
+
+/// Writes a unified diff (with `@@` hunk headers) from `expected` to
+/// `actual`. Removed lines are red, added lines green. If no line differs,
+/// the invisible difference is described instead.
+pub(super) fn write_diff(out: &mut dyn Write, expected: &str, actual: &str) {
+    let lines = diff_lines(expected, actual);
+    if lines.iter().all(|l| matches!(l, Line::Same(_))) {
+        writeln!(out, "({})", describe_invisible_difference(expected, actual)).unwrap();
+        return;
+    }
+
+    let red = Style::new().fg_color(Some(Color::Ansi(AnsiColor::Red)));
+    let green = Style::new().fg_color(Some(Color::Ansi(AnsiColor::Green)));
+    let cyan = Style::new().fg_color(Some(Color::Ansi(AnsiColor::Cyan)));
+
+    // Number of lines in the expected and actual value, respectively.
+    let num_old = |lines: &[Line<'_>]| lines.iter().filter(|l| !l.is_added()).count();
+    let num_new = |lines: &[Line<'_>]| lines.iter().filter(|l| !l.is_removed()).count();
+    for (start, end) in hunks(&lines) {
+        let (before, hunk) = (&lines[..start], &lines[start..end]);
+        writeln!(
+            out,
+            "{cyan}@@ -{},{} +{},{} @@{cyan:#}",
+            num_old(before) + 1,
+            num_old(hunk),
+            num_new(before) + 1,
+            num_new(hunk),
+        ).unwrap();
+
+        for line in hunk {
+            match line {
+                Line::Same(s) => writeln!(out, " {}", s),
+                Line::Removed(s) => writeln!(out, "{red}-{}{red:#}", s),
+                Line::Added(s) => writeln!(out, "{green}+{}{green:#}", s),
+            }.unwrap();
+        }
+    }
+}
+
+/// Describes how `expected` and `actual` differ if all their lines are equal,
+/// i.e. only in line endings or a trailing newline.
+fn describe_invisible_difference(expected: &str, actual: &str) -> String {
+    if expected == actual {
+        return "expected and actual are equal".into();
+    }
+
+    let normalize = |s: &str| s.replace("\r\n", "\n");
+    let (expected_normalized, actual_normalized) = (normalize(expected), normalize(actual));
+    if expected_normalized == actual_normalized {
+        return "only the line endings differ (`\\r\\n` vs. `\\n`)".into();
+    }
+
+    // As all lines are equal, one of the values has a trailing newline that
+    // the other one lacks.
+    let mut msg = if expected_normalized.ends_with('\n') {
+        "expected ends with a newline, actual does not".to_owned()
+    } else {
+        "actual ends with a newline, expected does not".to_owned()
+    };
+    if expected.contains("\r\n") != actual.contains("\r\n") {
+        msg.push_str("; the line endings differ, too (`\\r\\n` vs. `\\n`)");
+    }
+    msg
+}
+
+/// Computes a line diff via the longest common subsequence.
+fn diff_lines<'a>(expected: &'a str, actual: &'a str) -> Vec<Line<'a>> {
+    let old = expected.lines().collect::<Vec<_>>();
+    let new = actual.lines().collect::<Vec<_>>();
+
+    // Strip common prefix and suffix, which is cheap and usually makes the
+    // remaining problem tiny.
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..].iter().rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old_mid = &old[prefix..old.len() - suffix];
+    let new_mid = &new[prefix..new.len() - suffix];
+
+    let mut out = old[..prefix].iter().map(|l| Line::Same(l)).collect::<Vec<_>>();
+    if old_mid.len().saturating_mul(new_mid.len()) > MAX_TABLE_SIZE {
+        out.extend(old_mid.iter().map(|l| Line::Removed(l)));
+        out.extend(new_mid.iter().map(|l| Line::Added(l)));
+    } else {
+        // `table[i][j]` is the LCS length of `old_mid[i..]` and `new_mid[j..]`.
+        let (n, m) = (old_mid.len(), new_mid.len());
+        let mut table = vec![vec![0u32; m + 1]; n + 1];
+        for i in (0..n).rev() {
+            for j in (0..m).rev() {
+                table[i][j] = if old_mid[i] == new_mid[j] {
+                    table[i + 1][j + 1] + 1
+                } else {
+                    table[i + 1][j].max(table[i][j + 1])
+                };
+            }
+        }
+
+        let (mut i, mut j) = (0, 0);
+        while i < n || j < m {
+            if i < n && j < m && old_mid[i] == new_mid[j] {
+                out.push(Line::Same(old_mid[i]));
+                i += 1;
+                j += 1;
+            } else if j == m || (i < n && table[i + 1][j] >= table[i][j + 1]) {
+                out.push(Line::Removed(old_mid[i]));
+                i += 1;
+            } else {
+                out.push(Line::Added(new_mid[j]));
+                j += 1;
+            }
+        }
+    }
+    out.extend(old[old.len() - suffix..].iter().map(|l| Line::Same(l)));
+
+    out
+}
+
+/// Returns the ranges of `lines` to show: all changes with `CONTEXT` lines
+/// around them, where overlapping ranges are merged.
+fn hunks(lines: &[Line<'_>]) -> Vec<(usize, usize)> {
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+    for (i, line) in lines.iter().enumerate() {
+        if matches!(line, Line::Same(_)) {
+            continue;
+        }
+
+        let start = i.saturating_sub(CONTEXT);
+        let end = (i + 1 + CONTEXT).min(lines.len());
+        match hunks.last_mut() {
+            Some(last) if last.1 >= start => last.1 = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+
+    hunks
+}
@@ -0,0 +1,198 @@
+//! The JSON format, compatible to libtest's (unstable) `--format=json`.
+
+use std::{io::Write, time::Duration};
+
+use crate::{Conclusion, Failed, Measurement, Outcome, Reporter, TestInfo, TrialResult};
+
+use super::write_list;
+
+
+pub(crate) struct Json {
+    out: Box<dyn Write>,
+    show_output: bool,
+}
+
+impl Json {
+    pub(crate) fn new(out: Box<dyn Write>, show_output: bool) -> Self {
+        Self { out, show_output }
+    }
+}
+
+impl Reporter for Json {
+    fn run_started(&mut self, num_tests: u64, shuffle_seed: Option<u64>) {
+        let shuffle_seed = match shuffle_seed {
+            Some(seed) => format!(r#", "shuffle_seed": {seed}"#),
+            None => String::new(),
+        };
+        writeln!(
+            self.out,
+            r#"{{ "type": "suite", "event": "started", "test_count": {}{} }}"#,
+            num_tests,
+            shuffle_seed,
+        )
+        .unwrap();
+    }
+
+    fn setup_failed(&mut self, failed: &Failed) {
+        let message = match failed.message() {
+            Some(msg) => format!(r#", "message": "{}""#, escape8259::escape(msg)),
+            None => String::new(),
+        };
+        writeln!(
+            self.out,
+            r#"{{ "type": "suite", "event": "setup_failed"{}{} }}"#,
+            message,
+            failure_details(failed),
+        )
+        .unwrap();
+    }
+
+    fn trial_started(&mut self, info: &TestInfo) {
+        writeln!(
+            self.out,
+            r#"{{ "type": "test", "event": "started", "name": "{}" }}"#,
+            escape8259::escape(&info.name),
+        )
+        .unwrap();
+    }
+
+    fn trial_finished(&mut self, info: &TestInfo, result: &TrialResult) {
+        let outcome = &result.outcome;
+        if let Outcome::Measured(Measurement { avg, variance }) = outcome {
+            writeln!(
+                self.out,
+                r#"{{ "type": "bench", "name": "{}", "median": {}, "deviation": {} }}"#,
+                escape8259::escape(&info.name),
+                avg,
+                variance,
+            )
+            .unwrap();
+            return;
+        }
+
+        // Like libtest, we only include the output of successful tests with
+        // `--show-output`. The error message of failed tests is appended to
+        // their output.
+        let mut stdout = match outcome {
+            Outcome::Failed(_) | Outcome::TimedOut(_) => result.stdout.clone(),
+            _ if self.show_output => result.stdout.clone(),
+            _ => None,
+        };
+        if let Some(msg) = super::failure_message(result) {
+            let stdout = stdout.get_or_insert_with(String::new);
+            stdout.push_str(&format!("Error: \"{}\"\n", msg));
+        }
+
+        // Structured details of failures are emitted as separate keys.
+        let details = match outcome {
+            Outcome::Failed(failed) => failure_details(failed),
+            Outcome::Ignored(Some(reason)) => {
+                format!(r#", "message": "{}""#, escape8259::escape(reason))
+            }
+            _ => String::new(),
+        };
+
+        writeln!(
+            self.out,
+            r#"{{ "type": "test", "name": "{}", "event": "{}", "exec_time": {}{}{}{} }}"#,
+            escape8259::escape(&info.name),
+            match outcome {
+                Outcome::Passed if result.is_flaky() => "flaky",
+                Outcome::Passed => "ok",
+                Outcome::Failed(_) => "failed",
+                Outcome::Ignored(_) => "ignored",
+                Outcome::TimedOut(_) => "timeout",
+                Outcome::Measured(_) => unreachable!(),
+            },
+            result.duration.as_secs_f64(),
+            match result.attempts {
+                0 | 1 => String::new(),
+                n => format!(r#", "attempts": {n}"#),
+            },
+            match stdout {
+                Some(stdout) if !stdout.is_empty() => {
+                    format!(r#", "stdout": "{}""#, escape8259::escape(stdout))
+                }
+                _ => "".into(),
+            },
+            details,
+        )
+        .unwrap();
+    }
+
+    fn summary(&mut self, conclusion: &Conclusion, execution_time: Duration) {
+        let timed_out = match conclusion.num_timed_out {
+            0 => String::new(),
+            n => format!(r#" "timed_out": {n},"#),
+        };
+        let sharded_out = match conclusion.num_sharded_out {
+            0 => String::new(),
+            n => format!(r#" "sharded_out": {n},"#),
+        };
+        let flaky = match conclusion.num_flaky {
+            0 => String::new(),
+            n => format!(r#" "flaky": {n},"#),
+        };
+        let not_run = match conclusion.num_not_run {
+            0 => String::new(),
+            n => format!(r#" "not_run": {n},"#),
+        };
+        let setup_failed = if conclusion.setup_failed { r#" "setup_failed": true,"# } else { "" };
+
+        writeln!(
+            self.out,
+            concat!(
+                r#"{{ "type": "suite", "event": "{}", "passed": {}, "failed": {},{}{}"#,
+                r#" "ignored": {}, "measured": {}, "filtered_out": {},{}{}{} "exec_time": {} }}"#,
+            ),
+            if conclusion.has_failed() { "failed" } else { "ok" },
+            conclusion.num_passed,
+            conclusion.num_failed,
+            timed_out,
+            flaky,
+            conclusion.num_ignored,
+            conclusion.num_measured,
+            conclusion.num_filtered_out,
+            sharded_out,
+            not_run,
+            setup_failed,
+            execution_time.as_secs_f64()
+        )
+        .unwrap();
+    }
+
+    fn list(&mut self, trials: &[&TestInfo]) {
+        write_list(trials, false, &mut self.out).unwrap();
+    }
+}
+
+/// Returns the structured details of a failure as JSON object entries, each
+/// with a leading `, `.
+fn failure_details(failed: &Failed) -> String {
+    let mut out = String::new();
+    if let Some(expected) = failed.expected() {
+        out.push_str(&format!(r#", "expected": "{}""#, escape8259::escape(expected)));
+    }
+    if let Some(actual) = failed.actual() {
+        out.push_str(&format!(r#", "actual": "{}""#, escape8259::escape(actual)));
+    }
+    if let Some((file, line)) = failed.location() {
+        out.push_str(&format!(r#", "file": "{}", "line": {}"#, escape8259::escape(file), line));
+    }
+    if !failed.notes().is_empty() {
+        let notes = failed.notes().iter()
+            .map(|note| format!(r#""{}""#, escape8259::escape(note)))
+            .collect::<Vec<_>>();
+        out.push_str(&format!(r#", "notes": [{}]"#, notes.join(", ")));
+    }
+    if !failed.context().is_empty() {
+        let context = failed.context().iter()
+            .map(|(k, v)| format!(r#""{}": "{}""#, escape8259::escape(k), escape8259::escape(v)))
+            .collect::<Vec<_>>();
+        out.push_str(&format!(r#", "context": {{ {} }}"#, context.join(", ")));
+    }
+    if let Some(backtrace) = failed.backtrace() {
+        out.push_str(&format!(r#", "backtrace": "{}""#, escape8259::escape(backtrace)));
+    }
+    out
+}
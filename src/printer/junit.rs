@@ -0,0 +1,143 @@
+//! The JUnit XML format (`--format=junit`).
+
+use std::{io::Write, time::Duration};
+
+use crate::{Conclusion, Failed, Outcome, Reporter, TestInfo, TrialResult};
+
+use super::{failure_message, write_list};
+
+
+pub(crate) struct Junit {
+    out: Box<dyn Write>,
+
+    /// Collected `<testcase>` elements. The whole document is written at the
+    /// end, as the `<testsuite>` attributes require the final counts.
+    cases: Vec<String>,
+
+    /// Seed the trials were shuffled with, written as `<property>`.
+    shuffle_seed: Option<u64>,
+
+    /// Message of a failed suite setup, written as `<system-err>`.
+    setup_error: Option<String>,
+}
+
+impl Junit {
+    pub(crate) fn new(out: Box<dyn Write>) -> Self {
+        Self { out, cases: Vec::new(), shuffle_seed: None, setup_error: None }
+    }
+}
+
+impl Reporter for Junit {
+    fn run_started(&mut self, _: u64, shuffle_seed: Option<u64>) {
+        self.shuffle_seed = shuffle_seed;
+    }
+
+    fn setup_failed(&mut self, failed: &Failed) {
+        let msg = failed.message().unwrap_or("");
+        self.setup_error = Some(format!("setup failed: {msg}"));
+    }
+
+    fn trial_finished(&mut self, info: &TestInfo, result: &TrialResult) {
+        self.cases.push(test_case(info, result));
+    }
+
+    fn summary(&mut self, conclusion: &Conclusion, execution_time: Duration) {
+        writeln!(self.out, r#"<?xml version="1.0" encoding="UTF-8"?>"#).unwrap();
+        writeln!(self.out, "<testsuites>").unwrap();
+        writeln!(
+            self.out,
+            r#"  <testsuite name="test" tests="{}" failures="{}" errors="{}" skipped="{}" time="{:.3}">"#,
+            self.cases.len(),
+            conclusion.num_failed + conclusion.num_timed_out,
+            self.setup_error.iter().count(),
+            conclusion.num_ignored,
+            execution_time.as_secs_f64(),
+        ).unwrap();
+        if let Some(seed) = self.shuffle_seed {
+            writeln!(self.out, "    <properties>").unwrap();
+            writeln!(self.out, r#"      <property name="shuffle_seed" value="{seed}"/>"#).unwrap();
+            writeln!(self.out, "    </properties>").unwrap();
+        }
+        for case in &self.cases {
+            write!(self.out, "{}", case).unwrap();
+        }
+        if let Some(error) = &self.setup_error {
+            let error = escape_xml(error, false);
+            writeln!(self.out, "    <system-err>{}</system-err>", error).unwrap();
+        }
+        writeln!(self.out, "  </testsuite>").unwrap();
+        writeln!(self.out, "</testsuites>").unwrap();
+    }
+
+    fn list(&mut self, trials: &[&TestInfo]) {
+        write_list(trials, false, &mut self.out).unwrap();
+    }
+}
+
+/// Renders a single `<testcase>` element (including trailing newline).
+fn test_case(info: &TestInfo, result: &TrialResult) -> String {
+    let mut out = format!(
+        r#"    <testcase classname="{}" name="{}" time="{:.3}""#,
+        escape_xml(&info.kind, true),
+        escape_xml(&info.name, true),
+        result.duration.as_secs_f64(),
+    );
+
+    let failure_type = match &result.outcome {
+        Outcome::TimedOut(_) => "timeout",
+        _ => "assert",
+    };
+    let child = match &result.outcome {
+        Outcome::Passed | Outcome::Measured(_) => None,
+        Outcome::Ignored(None) => Some("<skipped/>".to_owned()),
+        Outcome::Ignored(Some(reason)) => {
+            Some(format!(r#"<skipped message="{}"/>"#, escape_xml(reason, true)))
+        }
+        Outcome::Failed(_) | Outcome::TimedOut(_) => match failure_message(result) {
+            None => Some(format!(r#"<failure type="{}"/>"#, failure_type)),
+            Some(msg) => Some(format!(
+                r#"<failure type="{}" message="{}"/>"#,
+                failure_type,
+                escape_xml(&msg, true),
+            )),
+        },
+    };
+    let system_out = result.stdout.as_deref()
+        .filter(|s| !s.is_empty())
+        .map(|s| format!("<system-out>{}</system-out>", escape_xml(s, false)));
+
+    if child.is_none() && system_out.is_none() {
+        out.push_str("/>\n");
+    } else {
+        out.push_str(">\n");
+        for element in child.iter().chain(&system_out) {
+            out.push_str(&format!("      {}\n", element));
+        }
+        out.push_str("    </testcase>\n");
+    }
+
+    out
+}
+
+/// Escapes the given string for use in XML text or, if `is_attribute` is
+/// set, attribute values. Characters that are not allowed in XML at all are
+/// replaced.
+fn escape_xml(s: &str, is_attribute: bool) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            '\n' | '\r' | '\t' if !is_attribute => out.push(c),
+            '\n' => out.push_str("&#10;"),
+            '\r' => out.push_str("&#13;"),
+            '\t' => out.push_str("&#9;"),
+            c if c.is_control() => out.push(char::REPLACEMENT_CHARACTER),
+            c => out.push(c),
+        }
+    }
+    out
+}
@@ -0,0 +1,216 @@
+//! The built-in reporters.
+//!
+//! This is everything that is printed to the screen (or logfile, if
+//! specified) by [`run`][crate::run]. These parameters influence printing:
+//! - `color`
+//! - `format` (and `quiet`)
+//! - `logfile`
+//! - `report`
+//!
+//! Each format is implemented as its own [`Reporter`] in a submodule.
+
+use std::{fs::{self, File}, io::Write, path::Path, time::Duration};
+
+use anstream::AutoStream;
+use anstyle::{AnsiColor, Color, Style};
+
+use crate::{
+    Arguments, ColorSetting, Conclusion, Failed, FormatSetting, Outcome, Reporter, TestInfo, Trial,
+    TrialResult,
+};
+
+mod diff;
+mod json;
+mod junit;
+mod pretty;
+mod tap;
+mod terse;
+
+
+/// Creates the reporter configured by the given arguments (`format`, `quiet`,
+/// `color` and `logfile` options).
+pub(crate) fn from_args(args: &Arguments, tests: &[Trial]) -> Box<dyn Reporter> {
+    let out = output(args);
+
+    // Determine correct format
+    let format = if args.quiet {
+        FormatSetting::Terse
+    } else {
+        args.format.unwrap_or(FormatSetting::Pretty)
+    };
+
+    with_format(format, out, args, tests)
+}
+
+/// Creates one reporter per `--report` option, each writing to its file
+/// without colors.
+pub(crate) fn report_files(args: &Arguments, tests: &[Trial]) -> Vec<Box<dyn Reporter>> {
+    args.report.iter()
+        .map(|report| {
+            let path = Path::new(&report.path);
+            if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+                fs::create_dir_all(parent).expect("failed to create directory for report");
+            }
+            let f = File::create(path).expect("failed to create report file");
+            with_format(report.format, Box::new(AutoStream::never(f)), args, tests)
+        })
+        .collect()
+}
+
+/// Creates the built-in reporter for `format` that writes to `out`.
+fn with_format(
+    format: FormatSetting,
+    out: Box<dyn Write>,
+    args: &Arguments,
+    tests: &[Trial],
+) -> Box<dyn Reporter> {
+    match format {
+        FormatSetting::Pretty => {
+            let time_thresholds = (args.report_time || args.ensure_time)
+                .then(|| args.time_thresholds.clone());
+            Box::new(pretty::Pretty::new(out, tests, time_thresholds))
+        }
+        FormatSetting::Terse => Box::new(terse::Terse::new(out)),
+        FormatSetting::Json => Box::new(json::Json::new(out, args.show_output)),
+        FormatSetting::Junit => Box::new(junit::Junit::new(out)),
+        FormatSetting::Tap => Box::new(tap::Tap::new(out, tests, args.show_output)),
+    }
+}
+
+/// Forwards all events to a primary reporter and any number of additional
+/// ones.
+pub(crate) struct Tee<R> {
+    primary: R,
+    others: Vec<Box<dyn Reporter>>,
+}
+
+impl<R: Reporter> Tee<R> {
+    pub(crate) fn new(primary: R, others: Vec<Box<dyn Reporter>>) -> Self {
+        Self { primary, others }
+    }
+
+    fn each(&mut self, mut f: impl FnMut(&mut dyn Reporter)) {
+        f(&mut self.primary);
+        for other in &mut self.others {
+            f(&mut **other);
+        }
+    }
+}
+
+impl<R: Reporter> Reporter for Tee<R> {
+    fn run_started(&mut self, num_tests: u64, shuffle_seed: Option<u64>) {
+        self.each(|r| r.run_started(num_tests, shuffle_seed));
+    }
+
+    fn setup_failed(&mut self, failed: &Failed) {
+        self.each(|r| r.setup_failed(failed));
+    }
+
+    fn trial_started(&mut self, info: &TestInfo) {
+        self.each(|r| r.trial_started(info));
+    }
+
+    fn trial_finished(&mut self, info: &TestInfo, result: &TrialResult) {
+        self.each(|r| r.trial_finished(info, result));
+    }
+
+    fn successes(&mut self, successes: &[(TestInfo, TrialResult)]) {
+        self.each(|r| r.successes(successes));
+    }
+
+    fn failures(&mut self, failures: &[(TestInfo, TrialResult)]) {
+        self.each(|r| r.failures(failures));
+    }
+
+    fn summary(&mut self, conclusion: &Conclusion, execution_time: Duration) {
+        self.each(|r| r.summary(conclusion, execution_time));
+    }
+
+    /// Only the primary reporter prints the list.
+    fn list(&mut self, trials: &[&TestInfo]) {
+        self.primary.list(trials);
+    }
+}
+
+/// Determines the target of all output.
+fn output(args: &Arguments) -> Box<dyn Write> {
+    let color_arg = args.color.unwrap_or(ColorSetting::Auto);
+
+    if let Some(logfile) = &args.logfile {
+        let f = File::create(logfile).expect("failed to create logfile");
+        if color_arg == ColorSetting::Always {
+            Box::new(AutoStream::always(f))
+        } else {
+            Box::new(AutoStream::never(f))
+        }
+    } else {
+        let choice = match color_arg {
+            ColorSetting::Auto => anstream::ColorChoice::Auto,
+            ColorSetting::Always => anstream::ColorChoice::Always,
+            ColorSetting::Never => anstream::ColorChoice::Never,
+        };
+        Box::new(AutoStream::new(std::io::stdout(), choice))
+    }
+}
+
+/// Writes a list of all given trials. Used if `--list` is set. With
+/// `ignore_reasons`, the reasons of ignored trials are appended, e.g.
+/// `foo: test (ignored, needs network)`.
+pub(crate) fn write_list(
+    trials: &[&TestInfo],
+    ignore_reasons: bool,
+    mut out: impl std::io::Write,
+) -> std::io::Result<()> {
+    for info in trials {
+        let reason = match info.ignore_reason() {
+            Some(reason) if ignore_reasons && info.is_ignored => format!(" (ignored, {reason})"),
+            _ => String::new(),
+        };
+        writeln!(
+            out,
+            "{}: {}{}",
+            info.test_name_with_kind(),
+            if info.is_bench { "bench" } else { "test" },
+            reason,
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Returns the message that is shown for a failed or timed out trial.
+fn failure_message(result: &TrialResult) -> Option<String> {
+    match &result.outcome {
+        Outcome::Failed(failed) => failed.msg.clone(),
+        Outcome::TimedOut(timeout) => Some(format!("test did not finish within {timeout:?}")),
+        _ => None,
+    }
+}
+
+/// Returns whether the given outcome counts as failure.
+pub(crate) fn is_failure(outcome: &Outcome) -> bool {
+    matches!(outcome, Outcome::Failed(_) | Outcome::TimedOut(_))
+}
+
+/// Formats the given integer with `,` as thousand separator.
+pub fn fmt_with_thousand_sep(mut v: u64) -> String {
+    let mut out = String::new();
+    while v >= 1000 {
+        out = format!(",{:03}{}", v % 1000, out);
+        v /= 1000;
+    }
+    out = format!("{}{}", v, out);
+
+    out
+}
+
+/// Returns the `ColorSpec` associated with the given outcome.
+fn color_of_outcome(outcome: &Outcome) -> Style {
+    let color = match outcome {
+        Outcome::Passed => AnsiColor::Green,
+        Outcome::Failed { .. } | Outcome::TimedOut(_) => AnsiColor::Red,
+        Outcome::Ignored(_) => AnsiColor::Yellow,
+        Outcome::Measured { .. } => AnsiColor::Cyan,
+    };
+    Style::new().fg_color(Some(Color::Ansi(color)))
+}
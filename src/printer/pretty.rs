@@ -0,0 +1,340 @@
+//! The default format: one line per test.
+
+use std::{io::Write, time::Duration};
+
+use anstyle::{AnsiColor, Color, Style};
+
+use crate::{
+    Conclusion, Failed, Measurement, Outcome, Reporter, TestInfo, TimeThresholdSetting, Trial,
+    TrialResult,
+};
+
+use super::{color_of_outcome, diff, failure_message, fmt_with_thousand_sep, write_list};
+
+
+pub(crate) struct Pretty {
+    out: Box<dyn Write>,
+    name_width: usize,
+    kind_width: usize,
+
+    /// If set, the execution time is printed after the outcome, highlighted
+    /// according to these thresholds (`--report-time`).
+    time_thresholds: Option<Vec<TimeThresholdSetting>>,
+}
+
+impl Pretty {
+    pub(crate) fn new(
+        out: Box<dyn Write>,
+        tests: &[Trial],
+        time_thresholds: Option<Vec<TimeThresholdSetting>>,
+    ) -> Self {
+        // Determine max test name length to do nice formatting later.
+        //
+        // Unicode is hard and there is no way we can properly align/pad the
+        // test names and outcomes. Counting the number of code points is just
+        // a cheap way that works in most cases. Usually, these names are
+        // ASCII.
+        let name_width = tests.iter()
+            .map(|test| test.info.name.chars().count() + should_panic_suffix(&test.info).len())
+            .max()
+            .unwrap_or(0);
+
+        let kind_width = tests.iter()
+            .map(|test| {
+                if test.info.kind.is_empty() {
+                    0
+                } else {
+                    // The two braces [] and one space
+                    test.info.kind.chars().count() + 3
+                }
+            })
+            .max()
+            .unwrap_or(0);
+
+        Self { out, name_width, kind_width, time_thresholds }
+    }
+}
+
+impl Reporter for Pretty {
+    /// Prints the first line "running 3 tests".
+    fn run_started(&mut self, num_tests: u64, shuffle_seed: Option<u64>) {
+        write_title(&mut self.out, num_tests, shuffle_seed);
+    }
+
+    fn setup_failed(&mut self, failed: &Failed) {
+        write_setup_failure(&mut self.out, failed);
+    }
+
+    /// Prints the text announcing the test (e.g. "test foo::bar ... ").
+    fn trial_started(&mut self, info: &TestInfo) {
+        let TestInfo { name, kind, .. } = info;
+        let name = format!("{}{}", name, should_panic_suffix(info));
+        let kind = if kind.is_empty() {
+            String::new()
+        } else {
+            format!("[{}] ", kind)
+        };
+
+        write!(
+            self.out,
+            "test {: <2$}{: <3$} ... ",
+            kind,
+            name,
+            self.kind_width,
+            self.name_width,
+        ).unwrap();
+        self.out.flush().unwrap();
+    }
+
+    /// Prints the outcome of a single tests, e.g. `ok` or `FAILED`, and with
+    /// `--report-time` also its execution time, e.g. `ok <0.123s>`.
+    /// Slow tests are printed yellow, too slow ones red.
+    fn trial_finished(&mut self, info: &TestInfo, result: &TrialResult) {
+        if result.is_flaky() {
+            write_flaky(&mut self.out);
+        } else {
+            write_outcome(&mut self.out, &result.outcome);
+        }
+        let has_time = !matches!(result.outcome, Outcome::Ignored(_) | Outcome::Measured(_));
+        if let Some(settings) = self.time_thresholds.as_ref().filter(|_| has_time) {
+            let threshold = info.effective_time_threshold(settings);
+            let style = if threshold.is_critical(result.duration) {
+                Style::new().fg_color(Some(Color::Ansi(AnsiColor::Red)))
+            } else if threshold.is_warn(result.duration) {
+                Style::new().fg_color(Some(Color::Ansi(AnsiColor::Yellow)))
+            } else {
+                Style::new()
+            };
+            write!(self.out, " {style}<{:.3}s>{style:#}", result.duration.as_secs_f64()).unwrap();
+        }
+        writeln!(self.out).unwrap();
+    }
+
+    fn successes(&mut self, successes: &[(TestInfo, TrialResult)]) {
+        write_successes(&mut self.out, successes);
+    }
+
+    fn failures(&mut self, failures: &[(TestInfo, TrialResult)]) {
+        write_failures(&mut self.out, failures);
+    }
+
+    fn summary(&mut self, conclusion: &Conclusion, execution_time: Duration) {
+        write_summary(&mut self.out, conclusion, execution_time);
+    }
+
+    fn list(&mut self, trials: &[&TestInfo]) {
+        write_list(trials, true, &mut self.out).unwrap();
+    }
+}
+
+/// Returns what libtest prints after the names of `#[should_panic]` tests.
+fn should_panic_suffix(info: &TestInfo) -> &'static str {
+    if info.has_should_panic_flag() {
+        " - should panic"
+    } else {
+        ""
+    }
+}
+
+/// Prints the first line "running 3 tests", including the shuffle seed if
+/// there is one (e.g. "running 3 tests (shuffle seed: 42)").
+pub(super) fn write_title(out: &mut dyn Write, num_tests: u64, shuffle_seed: Option<u64>) {
+    let plural_s = if num_tests == 1 { "" } else { "s" };
+    let seed = match shuffle_seed {
+        Some(seed) => format!(" (shuffle seed: {seed})"),
+        None => String::new(),
+    };
+
+    writeln!(out).unwrap();
+    writeln!(out, "running {} test{}{}", num_tests, plural_s, seed).unwrap();
+}
+
+/// Prints the error of a failed suite setup.
+pub(super) fn write_setup_failure(out: &mut dyn Write, failed: &Failed) {
+    writeln!(out).unwrap();
+    match failed.message() {
+        Some(msg) => writeln!(out, "setup failed: {}", msg).unwrap(),
+        None => writeln!(out, "setup failed").unwrap(),
+    }
+    write_failure_details(out, failed);
+}
+
+/// Prints the captured output of successful tests.
+pub(super) fn write_successes(out: &mut dyn Write, successes: &[(TestInfo, TrialResult)]) {
+    writeln!(out).unwrap();
+    writeln!(out, "successes:").unwrap();
+    writeln!(out).unwrap();
+
+    // Print output of all tests that printed something
+    for (test_info, result) in successes {
+        if let Some(stdout) = result.stdout.as_deref().filter(|s| !s.is_empty()) {
+            writeln!(out, "---- {} stdout ----", test_info.name).unwrap();
+            write!(out, "{}", stdout).unwrap();
+            writeln!(out).unwrap();
+        }
+    }
+
+    // Print summary list of successful tests
+    writeln!(out).unwrap();
+    writeln!(out, "successes:").unwrap();
+    for (test_info, _) in successes {
+        writeln!(out, "    {}", test_info.name).unwrap();
+    }
+}
+
+/// Prints a list of failed tests with their messages and captured output.
+pub(super) fn write_failures(out: &mut dyn Write, failures: &[(TestInfo, TrialResult)]) {
+    writeln!(out).unwrap();
+    writeln!(out, "failures:").unwrap();
+    writeln!(out).unwrap();
+
+    // Print output and messages of all tests
+    for (test_info, result) in failures {
+        match &result.stdout {
+            Some(stdout) => {
+                writeln!(out, "---- {} stdout ----", test_info.name).unwrap();
+                if !stdout.is_empty() {
+                    write!(out, "{}", stdout).unwrap();
+                    if !stdout.ends_with('\n') {
+                        writeln!(out).unwrap();
+                    }
+                }
+            }
+            None => writeln!(out, "---- {} ----", test_info.name).unwrap(),
+        }
+        if let Some(msg) = failure_message(result) {
+            writeln!(out, "{}", msg).unwrap();
+        }
+        if let Outcome::Failed(failed) = &result.outcome {
+            write_failure_details(out, failed);
+        }
+        writeln!(out).unwrap();
+    }
+
+    // Print summary list of failed tests
+    writeln!(out).unwrap();
+    writeln!(out, "failures:").unwrap();
+    for (test_info, _) in failures {
+        writeln!(out, "    {}", test_info.name).unwrap();
+    }
+}
+
+/// Prints the structured details of a failure (everything but the message).
+fn write_failure_details(out: &mut dyn Write, failed: &Failed) {
+    if let Some((file, line)) = failed.location() {
+        writeln!(out, "  --> {}:{}", file, line).unwrap();
+    }
+    for note in failed.notes() {
+        writeln!(out, "note: {}", note).unwrap();
+    }
+    for (key, value) in failed.context() {
+        writeln!(out, "{}: {}", key, value).unwrap();
+    }
+
+    match (failed.expected(), failed.actual()) {
+        (Some(expected), Some(actual)) => {
+            writeln!(out).unwrap();
+            writeln!(out, "diff (-expected +actual):").unwrap();
+            diff::write_diff(out, expected, actual);
+        }
+        (expected, actual) => {
+            for (label, value) in [("expected", expected), ("actual", actual)] {
+                if let Some(value) = value {
+                    writeln!(out, "{}:", label).unwrap();
+                    writeln!(out, "{}", value.trim_end_matches('\n')).unwrap();
+                }
+            }
+        }
+    }
+
+    if let Some(backtrace) = failed.backtrace() {
+        writeln!(out, "stack backtrace:").unwrap();
+        writeln!(out, "{}", backtrace.trim_end_matches('\n')).unwrap();
+    }
+}
+
+/// Prints the summary line after all tests have been executed.
+pub(super) fn write_summary(out: &mut dyn Write, conclusion: &Conclusion, execution_time: Duration) {
+    let outcome = if conclusion.has_failed() {
+        Outcome::Failed(Failed::without_message())
+    } else {
+        Outcome::Passed
+    };
+
+    writeln!(out).unwrap();
+    write!(out, "test result: ").unwrap();
+    write_outcome(out, &outcome);
+    writeln!(out, ". {}", summary_counts(conclusion, execution_time)).unwrap();
+    writeln!(out).unwrap();
+}
+
+/// Formats the counts of the summary line, e.g. "3 passed; 0 failed; ...".
+pub(super) fn summary_counts(conclusion: &Conclusion, execution_time: Duration) -> String {
+    // Timeouts, flaky, sharded and skipped trials as well as setup failures are only mentioned if
+    // relevant, to keep the line identical to libtest's in the common case.
+    let timed_out = match conclusion.num_timed_out {
+        0 => String::new(),
+        n => format!(" {n} timed out;"),
+    };
+
+    let sharded_out = match conclusion.num_sharded_out {
+        0 => String::new(),
+        n => format!(" {n} sharded out;"),
+    };
+    let flaky = match conclusion.num_flaky {
+        0 => String::new(),
+        n => format!(" {n} flaky;"),
+    };
+    let not_run = match conclusion.num_not_run {
+        0 => String::new(),
+        n => format!(" {n} not run;"),
+    };
+    let setup_failed = if conclusion.setup_failed { " setup failed;" } else { "" };
+
+    format!(
+        "{} passed; {} failed;{}{} {} ignored; {} measured; \
+            {} filtered out;{}{}{} finished in {:.2}s",
+        conclusion.num_passed,
+        conclusion.num_failed,
+        timed_out,
+        flaky,
+        conclusion.num_ignored,
+        conclusion.num_measured,
+        conclusion.num_filtered_out,
+        sharded_out,
+        not_run,
+        setup_failed,
+        execution_time.as_secs_f64()
+    )
+}
+
+/// Prints a colored 'FLAKY' for trials that only passed after a retry.
+pub(super) fn write_flaky(out: &mut dyn Write) {
+    let style = Style::new().fg_color(Some(Color::Ansi(AnsiColor::Yellow)));
+    write!(out, "{style}FLAKY{style:#}").unwrap();
+}
+
+/// Prints a colored 'ok'/'FAILED'/'ignored'/'bench'/'TIMEOUT'. Ignored trials
+/// with a reason are printed like 'ignored, reason'.
+pub(super) fn write_outcome(out: &mut dyn Write, outcome: &Outcome) {
+    let s = match outcome {
+        Outcome::Passed => "ok".into(),
+        Outcome::Failed { .. } => "FAILED".into(),
+        Outcome::Ignored(None) => "ignored".into(),
+        Outcome::Ignored(Some(reason)) => format!("ignored, {reason}"),
+        Outcome::TimedOut(_) => "TIMEOUT".into(),
+        Outcome::Measured { .. } => "bench".into(),
+    };
+
+    let style = color_of_outcome(outcome);
+    write!(out, "{style}{}{style:#}", s).unwrap();
+
+    if let Outcome::Measured(Measurement { avg, variance }) = outcome {
+        write!(
+            out,
+            ": {:>11} ns/iter (+/- {})",
+            fmt_with_thousand_sep(*avg),
+            fmt_with_thousand_sep(*variance),
+        ).unwrap();
+    }
+}
@@ -0,0 +1,143 @@
+//! The Test Anything Protocol format (`--format=tap`), version 14.
+
+use std::{collections::HashMap, io::Write, time::Duration};
+
+use crate::{Conclusion, Failed, Measurement, Outcome, Reporter, TestInfo, Trial, TrialResult};
+
+use super::{failure_message, is_failure, pretty, write_list};
+
+
+pub(crate) struct Tap {
+    out: Box<dyn Write>,
+    show_output: bool,
+
+    /// Names (including the kind) of all trials that are about to run.
+    names: Vec<String>,
+
+    /// Number of the last test point printed.
+    counter: u64,
+}
+
+impl Tap {
+    pub(crate) fn new(out: Box<dyn Write>, tests: &[Trial], show_output: bool) -> Self {
+        let names = tests.iter().map(|t| t.info.test_name_with_kind().into_owned()).collect();
+        Self { out, show_output, names, counter: 0 }
+    }
+}
+
+impl Reporter for Tap {
+    /// Prints the version and the plan line, followed by the shuffle seed as
+    /// comment.
+    fn run_started(&mut self, num_tests: u64, shuffle_seed: Option<u64>) {
+        writeln!(self.out, "TAP version 14").unwrap();
+        writeln!(self.out, "1..{}", num_tests).unwrap();
+        if let Some(seed) = shuffle_seed {
+            writeln!(self.out, "# shuffle seed: {}", seed).unwrap();
+        }
+    }
+
+    /// Aborts the TAP stream with `Bail out!`, as no test point will follow.
+    fn setup_failed(&mut self, failed: &Failed) {
+        let msg = failed.message().map(escape_description).unwrap_or_default();
+        writeln!(self.out, "Bail out! setup failed: {}", msg).unwrap();
+    }
+
+    /// Prints the test point, e.g. `ok 3 - [kind] name`, followed by a YAML
+    /// diagnostic block if there is anything to report.
+    fn trial_finished(&mut self, info: &TestInfo, result: &TrialResult) {
+        let outcome = &result.outcome;
+        self.counter += 1;
+        let ok = if is_failure(outcome) { "not ok" } else { "ok" };
+        let directive = match outcome {
+            Outcome::Ignored(None) => " # SKIP".to_owned(),
+            Outcome::Ignored(Some(reason)) => format!(" # SKIP {}", escape_description(reason)),
+            _ => String::new(),
+        };
+        writeln!(
+            self.out,
+            "{} {} - {}{}",
+            ok,
+            self.counter,
+            escape_description(&info.test_name_with_kind()),
+            directive,
+        ).unwrap();
+
+        let mut diagnostics = Vec::new();
+        if let Some(msg) = failure_message(result) {
+            diagnostics.push(format!(r#"message: "{}""#, escape8259::escape(msg)));
+        }
+        if let Outcome::Failed(failed) = outcome {
+            if let Some(expected) = failed.expected() {
+                diagnostics.push(format!(r#"expected: "{}""#, escape8259::escape(expected)));
+            }
+            if let Some(actual) = failed.actual() {
+                diagnostics.push(format!(r#"actual: "{}""#, escape8259::escape(actual)));
+            }
+            if let Some((file, line)) = failed.location() {
+                diagnostics.push(format!(r#"at: "{}:{}""#, escape8259::escape(file), line));
+            }
+        }
+        if result.attempts > 1 {
+            diagnostics.push(format!("attempts: {}", result.attempts));
+        }
+        if let Outcome::Measured(Measurement { avg, variance }) = outcome {
+            diagnostics.push(format!("median: {}", avg));
+            diagnostics.push(format!("deviation: {}", variance));
+        }
+        if let Some(stdout) = result.stdout.as_deref().filter(|s| !s.is_empty()) {
+            if is_failure(outcome) || self.show_output {
+                diagnostics.push(format!(r#"stdout: "{}""#, escape8259::escape(stdout)));
+            }
+        }
+
+        if !diagnostics.is_empty() {
+            writeln!(self.out, "  ---").unwrap();
+            for line in diagnostics {
+                writeln!(self.out, "  {}", line).unwrap();
+            }
+            writeln!(self.out, "  ...").unwrap();
+        }
+    }
+
+    /// Prints a skipped test point for each trial that was not run (e.g.
+    /// with `--fail-fast`), as the plan promised one for every trial, and
+    /// then the summary as a comment line.
+    fn summary(&mut self, conclusion: &Conclusion, execution_time: Duration) {
+        // After `Bail out!`, no test points may follow.
+        if !conclusion.setup_failed && conclusion.num_not_run > 0 {
+            let mut finished = HashMap::<_, usize>::new();
+            for (info, _) in &conclusion.trial_results {
+                *finished.entry(info.test_name_with_kind()).or_default() += 1;
+            }
+            for name in &self.names {
+                match finished.get_mut(name.as_str()) {
+                    Some(n) if *n > 0 => *n -= 1,
+                    _ => {
+                        self.counter += 1;
+                        writeln!(
+                            self.out,
+                            "ok {} - {} # SKIP not run",
+                            self.counter,
+                            escape_description(name),
+                        ).unwrap();
+                    }
+                }
+            }
+        }
+
+        let counts = pretty::summary_counts(conclusion, execution_time);
+        writeln!(self.out, "# {}", counts).unwrap();
+    }
+
+    fn list(&mut self, trials: &[&TestInfo]) {
+        write_list(trials, false, &mut self.out).unwrap();
+    }
+}
+
+/// Escapes a test point description: `#` would start a directive and line
+/// breaks would end the test point.
+fn escape_description(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('#', "\\#")
+        .replace(['\n', '\r'], " ")
+}
@@ -0,0 +1,74 @@
+//! The terse format (`-q`): one character per test.
+
+use std::{io::Write, time::Duration};
+
+use anstyle::{AnsiColor, Color, Style};
+
+use crate::{Conclusion, Failed, Outcome, Reporter, TestInfo, TrialResult};
+
+use super::{color_of_outcome, pretty, write_list};
+
+
+pub(crate) struct Terse {
+    out: Box<dyn Write>,
+}
+
+impl Terse {
+    pub(crate) fn new(out: Box<dyn Write>) -> Self {
+        Self { out }
+    }
+}
+
+impl Reporter for Terse {
+    fn run_started(&mut self, num_tests: u64, shuffle_seed: Option<u64>) {
+        pretty::write_title(&mut self.out, num_tests, shuffle_seed);
+    }
+
+    fn setup_failed(&mut self, failed: &Failed) {
+        pretty::write_setup_failure(&mut self.out, failed);
+    }
+
+    // In terse mode, nothing is printed before the job. Only `trial_finished`
+    // prints one character.
+
+    /// Prints `.`, `F`, `i`, `T` or `f` (flaky).
+    fn trial_finished(&mut self, _: &TestInfo, result: &TrialResult) {
+        let outcome = &result.outcome;
+        let c = match outcome {
+            Outcome::Passed if result.is_flaky() => 'f',
+            Outcome::Passed => '.',
+            Outcome::Failed { .. } => 'F',
+            Outcome::Ignored(_) => 'i',
+            Outcome::TimedOut(_) => 'T',
+            Outcome::Measured { .. } => {
+                // Benchmark are never printed in terse mode... for
+                // some reason.
+                pretty::write_outcome(&mut self.out, outcome);
+                writeln!(self.out).unwrap();
+                return;
+            }
+        };
+
+        let style = match result.is_flaky() {
+            true => Style::new().fg_color(Some(Color::Ansi(AnsiColor::Yellow))),
+            false => color_of_outcome(outcome),
+        };
+        write!(self.out, "{style}{}{style:#}", c).unwrap();
+    }
+
+    fn successes(&mut self, successes: &[(TestInfo, TrialResult)]) {
+        pretty::write_successes(&mut self.out, successes);
+    }
+
+    fn failures(&mut self, failures: &[(TestInfo, TrialResult)]) {
+        pretty::write_failures(&mut self.out, failures);
+    }
+
+    fn summary(&mut self, conclusion: &Conclusion, execution_time: Duration) {
+        pretty::write_summary(&mut self.out, conclusion, execution_time);
+    }
+
+    fn list(&mut self, trials: &[&TestInfo]) {
+        write_list(trials, false, &mut self.out).unwrap();
+    }
+}
@@ -0,0 +1,132 @@
+//! Definition of the `Reporter` trait.
+
+use std::time::Duration;
+
+use crate::{Conclusion, Failed, TestInfo, TrialResult};
+
+
+/// Receives events during a test run and produces output from them.
+///
+/// [`run`][crate::run] uses a built-in reporter that is selected via
+/// `--format` (and configured by `--color`, `--logfile`, ...). To produce your
+/// own output, implement this trait and pass your reporter to
+/// [`run_with_reporter`][crate::run_with_reporter].
+///
+/// The methods are called in the following order: [`run_started`] once, then
+/// [`trial_started`] and [`trial_finished`] for every trial, then
+/// [`successes`] (only with `--show-output` and if any output was captured),
+/// [`failures`] (only if there were any) and finally [`summary`]. If `--list`
+/// is set, only [`list`] is called. If the suite setup fails (see
+/// [`RunOptions::before_all`][crate::RunOptions::before_all]),
+/// [`setup_failed`] is called after [`run_started`], followed directly by
+/// [`summary`].
+///
+/// When trials are executed in parallel, [`trial_started`] is only called
+/// right before [`trial_finished`], once the trial has already finished. Use
+/// [`TrialResult::duration`] for timing information.
+///
+/// [`run_started`]: Reporter::run_started
+/// [`trial_started`]: Reporter::trial_started
+/// [`trial_finished`]: Reporter::trial_finished
+/// [`successes`]: Reporter::successes
+/// [`failures`]: Reporter::failures
+/// [`summary`]: Reporter::summary
+/// [`list`]: Reporter::list
+/// [`setup_failed`]: Reporter::setup_failed
+pub trait Reporter {
+    /// Called once before any trial is executed. `num_tests` is the number of
+    /// trials that are left after filtering. `shuffle_seed` is the seed the
+    /// trials were shuffled with (`--shuffle`), if any.
+    fn run_started(&mut self, num_tests: u64, shuffle_seed: Option<u64>) {
+        let _ = (num_tests, shuffle_seed);
+    }
+
+    /// Called if the suite setup failed, in which case no trial is run.
+    fn setup_failed(&mut self, failed: &Failed) {
+        let _ = failed;
+    }
+
+    /// Called when a trial starts (see the note about parallel execution in
+    /// the trait documentation).
+    fn trial_started(&mut self, info: &TestInfo) {
+        let _ = info;
+    }
+
+    /// Called when a trial has finished (or was ignored).
+    fn trial_finished(&mut self, info: &TestInfo, result: &TrialResult);
+
+    /// Called with all successful trials after all trials have finished, but
+    /// only if `--show-output` is set and output of any of them was captured.
+    fn successes(&mut self, successes: &[(TestInfo, TrialResult)]) {
+        let _ = successes;
+    }
+
+    /// Called with all failed (or timed out) trials after all trials have
+    /// finished, but only if there were any.
+    fn failures(&mut self, failures: &[(TestInfo, TrialResult)]) {
+        let _ = failures;
+    }
+
+    /// Called once at the very end of the run.
+    fn summary(&mut self, conclusion: &Conclusion, execution_time: Duration);
+
+    /// Called instead of all other methods if `--list` is set. By default,
+    /// prints the list like libtest does (e.g. `[kind] name: test`) to stdout.
+    fn list(&mut self, trials: &[&TestInfo]) {
+        let _ = crate::printer::write_list(trials, false, std::io::stdout().lock());
+    }
+}
+
+impl<R: Reporter + ?Sized> Reporter for Box<R> {
+    fn run_started(&mut self, num_tests: u64, shuffle_seed: Option<u64>) {
+        (**self).run_started(num_tests, shuffle_seed)
+    }
+    fn setup_failed(&mut self, failed: &Failed) {
+        (**self).setup_failed(failed)
+    }
+    fn trial_started(&mut self, info: &TestInfo) {
+        (**self).trial_started(info)
+    }
+    fn trial_finished(&mut self, info: &TestInfo, result: &TrialResult) {
+        (**self).trial_finished(info, result)
+    }
+    fn successes(&mut self, successes: &[(TestInfo, TrialResult)]) {
+        (**self).successes(successes)
+    }
+    fn failures(&mut self, failures: &[(TestInfo, TrialResult)]) {
+        (**self).failures(failures)
+    }
+    fn summary(&mut self, conclusion: &Conclusion, execution_time: Duration) {
+        (**self).summary(conclusion, execution_time)
+    }
+    fn list(&mut self, trials: &[&TestInfo]) {
+        (**self).list(trials)
+    }
+}
+
+impl<R: Reporter + ?Sized> Reporter for &mut R {
+    fn run_started(&mut self, num_tests: u64, shuffle_seed: Option<u64>) {
+        (**self).run_started(num_tests, shuffle_seed)
+    }
+    fn setup_failed(&mut self, failed: &Failed) {
+        (**self).setup_failed(failed)
+    }
+    fn trial_started(&mut self, info: &TestInfo) {
+        (**self).trial_started(info)
+    }
+    fn trial_finished(&mut self, info: &TestInfo, result: &TrialResult) {
+        (**self).trial_finished(info, result)
+    }
+    fn successes(&mut self, successes: &[(TestInfo, TrialResult)]) {
+        (**self).successes(successes)
+    }
+    fn failures(&mut self, failures: &[(TestInfo, TrialResult)]) {
+        (**self).failures(failures)
+    }
+    fn summary(&mut self, conclusion: &Conclusion, execution_time: Duration) {
+        (**self).summary(conclusion, execution_time)
+    }
+    fn list(&mut self, trials: &[&TestInfo]) {
+        (**self).list(trials)
+    }
+}
@@ -0,0 +1,131 @@
+//! Handing out trials to worker threads such that the weights (see
+//! [`Trial::with_weight`][crate::Trial::with_weight]) of running trials fit
+//! into the test threads and no resource (see
+//! [`Trial::with_resource`][crate::Trial::with_resource]) is used by more
+//! trials at once than allowed.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Condvar, Mutex},
+};
+
+use crate::{TestInfo, Trial};
+
+
+pub(crate) struct Scheduler {
+    state: Mutex<State>,
+
+    /// Notified whenever threads or resources are released.
+    released: Condvar,
+}
+
+struct State {
+    pending: VecDeque<Trial>,
+
+    /// Number of test threads not used by running trials.
+    free_threads: usize,
+
+    /// Number of test threads used by running trials of each kind.
+    kind_in_use: HashMap<String, usize>,
+
+    /// Maximum number of test threads used by trials of a kind at once (see
+    /// `--kind-threads`). Kinds not in here can use all of them.
+    kind_threads: HashMap<String, usize>,
+
+    /// Total number of test threads.
+    threads: usize,
+
+    /// Number of running trials using each resource.
+    in_use: HashMap<String, u32>,
+
+    /// Maximum number of trials using a resource at once. Resources not in
+    /// here can only be used by one trial at a time.
+    limits: HashMap<String, u32>,
+}
+
+impl State {
+    fn is_available(&self, resource: &str) -> bool {
+        let in_use = self.in_use.get(resource).copied().unwrap_or(0);
+        in_use < self.limits.get(resource).copied().unwrap_or(1)
+    }
+
+    /// Number of test threads the trial occupies. Capped at what is allowed
+    /// at all, as the trial could never run otherwise.
+    fn cost(&self, info: &TestInfo) -> usize {
+        let limit = self.kind_threads.get(&info.kind).copied().unwrap_or(self.threads);
+        info.weight.min(limit).min(self.threads)
+    }
+
+    fn can_start(&self, info: &TestInfo) -> bool {
+        let cost = self.cost(info);
+        let kind_limit = self.kind_threads.get(&info.kind).copied().unwrap_or(self.threads);
+        let kind_in_use = self.kind_in_use.get(&info.kind).copied().unwrap_or(0);
+        cost <= self.free_threads
+            && kind_in_use + cost <= kind_limit
+            && info.resources.iter().all(|r| self.is_available(r))
+    }
+}
+
+impl Scheduler {
+    pub(crate) fn new(
+        trials: Vec<Trial>,
+        threads: usize,
+        kind_threads: HashMap<String, usize>,
+        limits: HashMap<String, u32>,
+    ) -> Self {
+        let state = State {
+            pending: trials.into(),
+            free_threads: threads,
+            kind_in_use: HashMap::new(),
+            kind_threads,
+            threads,
+            in_use: HashMap::new(),
+            limits,
+        };
+        Self { state: Mutex::new(state), released: Condvar::new() }
+    }
+
+    /// Returns the first pending trial that can be started, i.e. whose weight
+    /// fits into the free threads and whose resources are all available, and
+    /// marks them as used. If there is none, blocks until other trials have
+    /// released theirs. Returns `None` once no trials are left.
+    pub(crate) fn next(&self) -> Option<Trial> {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if state.pending.is_empty() {
+                return None;
+            }
+
+            let pos = state.pending.iter().position(|t| state.can_start(&t.info));
+            if let Some(pos) = pos {
+                let trial = state.pending.remove(pos).expect("position is in bounds");
+                let cost = state.cost(&trial.info);
+                state.free_threads -= cost;
+                *state.kind_in_use.entry(trial.info.kind.clone()).or_insert(0) += cost;
+                for resource in &trial.info.resources {
+                    *state.in_use.entry(resource.clone()).or_insert(0) += 1;
+                }
+                return Some(trial);
+            }
+
+            state = self.released.wait(state).unwrap();
+        }
+    }
+
+    /// Releases the threads and resources of a trial returned by
+    /// [`Scheduler::next`] after it finished.
+    pub(crate) fn release(&self, info: &TestInfo) {
+        let mut state = self.state.lock().unwrap();
+        let cost = state.cost(info);
+        state.free_threads += cost;
+        if let Some(in_use) = state.kind_in_use.get_mut(&info.kind) {
+            *in_use -= cost;
+        }
+        for resource in &info.resources {
+            if let Some(in_use) = state.in_use.get_mut(resource) {
+                *in_use -= 1;
+            }
+        }
+        self.released.notify_all();
+    }
+}
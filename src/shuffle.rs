@@ -0,0 +1,56 @@
+//! Shuffling of trials (`--shuffle`). This uses its own small PRNG so that a
+//! given seed results in the same order on every platform and with every
+//! version of this crate's dependencies.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+
+/// Returns a seed for when `--shuffle` is given without `--shuffle-seed`.
+pub(crate) fn random_seed() -> u64 {
+    // Like libtest, we simply use the current time.
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or(0)
+}
+
+/// Shuffles `items` in a way that only depends on `seed` and `items.len()`.
+pub(crate) fn shuffle<T>(items: &mut [T], seed: u64) {
+    // Fisher-Yates shuffle
+    let mut rng = Rng::new(seed);
+    for i in (1..items.len()).rev() {
+        let j = rng.below(i as u64 + 1) as usize;
+        items.swap(i, j);
+    }
+}
+
+/// Hashes `s` with FNV-1a, which is simple and, unlike `DefaultHasher`,
+/// guaranteed to be stable across Rust versions and platforms.
+pub(crate) fn hash(s: &str) -> u64 {
+    s.bytes().fold(0xcbf29ce484222325, |hash, b| (hash ^ b as u64).wrapping_mul(0x100000001b3))
+}
+
+/// The SplitMix64 generator: tiny, fast and good enough for shuffling.
+pub(crate) struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub(crate) fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a number in `0..bound`. The slight bias for huge bounds does
+    /// not matter for shuffling.
+    fn below(&mut self, bound: u64) -> u64 {
+        ((self.next_u64() as u128 * bound as u128) >> 64) as u64
+    }
+}
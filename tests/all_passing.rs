@@ -1,5 +1,5 @@
-use common::{args, check};
-use libtest_mimic::{Trial, Conclusion};
+use common::{args, check, Counts};
+use libtest_mimic::{Trial};
 use pretty_assertions::assert_eq;
 
 use crate::common::do_run;
@@ -19,12 +19,17 @@ fn tests() -> Vec<Trial> {
 #[test]
 fn normal() {
     check(args([]), tests, 3,
-        Conclusion {
+        Counts {
             num_filtered_out: 0,
             num_passed: 3,
             num_failed: 0,
             num_ignored: 0,
             num_measured: 0,
+            num_timed_out: 0,
+            num_sharded_out: 0,
+            num_not_run: 0,
+            num_flaky: 0,
+            setup_failed: false,
         },
         "
             test foo   ... ok
@@ -37,12 +42,17 @@ fn normal() {
 #[test]
 fn filter_one() {
     check(args(["foo"]), tests, 1,
-        Conclusion {
+        Counts {
             num_filtered_out: 2,
             num_passed: 1,
             num_failed: 0,
             num_ignored: 0,
             num_measured: 0,
+            num_timed_out: 0,
+            num_sharded_out: 0,
+            num_not_run: 0,
+            num_flaky: 0,
+            setup_failed: false,
         },
         "test foo ... ok",
     );
@@ -51,12 +61,17 @@ fn filter_one() {
 #[test]
 fn filter_two() {
     check(args(["bar"]), tests, 2,
-        Conclusion {
+        Counts {
             num_filtered_out: 1,
             num_passed: 2,
             num_failed: 0,
             num_ignored: 0,
             num_measured: 0,
+            num_timed_out: 0,
+            num_sharded_out: 0,
+            num_not_run: 0,
+            num_flaky: 0,
+            setup_failed: false,
         },
         "
             test bar   ... ok
@@ -69,12 +84,17 @@ fn filter_two() {
 #[test]
 fn filter_exact() {
     check(args(["bar", "--exact"]), tests, 1,
-        Conclusion {
+        Counts {
             num_filtered_out: 2,
             num_passed: 1,
             num_failed: 0,
             num_ignored: 0,
             num_measured: 0,
+            num_timed_out: 0,
+            num_sharded_out: 0,
+            num_not_run: 0,
+            num_flaky: 0,
+            setup_failed: false,
         },
         "test bar ... ok",
     );
@@ -83,12 +103,17 @@ fn filter_exact() {
 #[test]
 fn filter_two_and_skip() {
     check(args(["--skip", "barro", "bar"]), tests, 1,
-        Conclusion {
+        Counts {
             num_filtered_out: 2,
             num_passed: 1,
             num_failed: 0,
             num_ignored: 0,
             num_measured: 0,
+            num_timed_out: 0,
+            num_sharded_out: 0,
+            num_not_run: 0,
+            num_flaky: 0,
+            setup_failed: false,
         },
         "test bar ... ok",
     );
@@ -97,12 +122,17 @@ fn filter_two_and_skip() {
 #[test]
 fn skip_nothing() {
     check(args(["--skip", "peter"]), tests, 3,
-        Conclusion {
+        Counts {
             num_filtered_out: 0,
             num_passed: 3,
             num_failed: 0,
             num_ignored: 0,
             num_measured: 0,
+            num_timed_out: 0,
+            num_sharded_out: 0,
+            num_not_run: 0,
+            num_flaky: 0,
+            setup_failed: false,
         },
         "
             test foo   ... ok
@@ -115,12 +145,17 @@ fn skip_nothing() {
 #[test]
 fn skip_two() {
     check(args(["--skip", "bar"]), tests, 1,
-        Conclusion {
+        Counts {
             num_filtered_out: 2,
             num_passed: 1,
             num_failed: 0,
             num_ignored: 0,
             num_measured: 0,
+            num_timed_out: 0,
+            num_sharded_out: 0,
+            num_not_run: 0,
+            num_flaky: 0,
+            setup_failed: false,
         },
         "test foo ... ok"
     );
@@ -129,12 +164,17 @@ fn skip_two() {
 #[test]
 fn skip_exact() {
     check(args(["--exact", "--skip", "bar"]), tests, 2,
-        Conclusion {
+        Counts {
             num_filtered_out: 1,
             num_passed: 2,
             num_failed: 0,
             num_ignored: 0,
             num_measured: 0,
+            num_timed_out: 0,
+            num_sharded_out: 0,
+            num_not_run: 0,
+            num_flaky: 0,
+            setup_failed: false,
         },
         "
             test foo   ... ok
@@ -146,12 +186,17 @@ fn skip_exact() {
 #[test]
 fn terse_output() {
     let (c, out) = do_run(args(["--format", "terse"]), tests());
-    assert_eq!(c, Conclusion {
+    assert_eq!(Counts::from(&c), Counts {
         num_filtered_out: 0,
         num_passed: 3,
         num_failed: 0,
         num_ignored: 0,
         num_measured: 0,
+        num_timed_out: 0,
+        num_sharded_out: 0,
+        num_not_run: 0,
+        num_flaky: 0,
+        setup_failed: false,
     });
     assert_log!(out, "
         running 3 tests
@@ -160,3 +205,43 @@ fn terse_output() {
             finished in 0.00s
     ");
 }
+
+#[test]
+fn report_time() {
+    check(args(["--report-time"]), tests, 3,
+        Counts {
+            num_filtered_out: 0,
+            num_passed: 3,
+            num_failed: 0,
+            num_ignored: 0,
+            num_measured: 0,
+            num_timed_out: 0,
+            num_sharded_out: 0,
+            num_not_run: 0,
+            num_flaky: 0,
+            setup_failed: false,
+        },
+        "
+            test foo   ... ok <0.000s>
+            test bar   ... ok <0.000s>
+            test barro ... ok <0.000s>
+        "
+    );
+}
+
+#[test]
+fn trial_results() {
+    let trials = vec![
+        Trial::test("sleeps", || {
+            std::thread::sleep(std::time::Duration::from_millis(20));
+            Ok(())
+        }),
+        Trial::test("ignored", || Ok(())).with_ignored_flag(true),
+        Trial::test("filtered", || Ok(())),
+    ];
+    let (c, _) = do_run(args(["--test-threads", "1", "--skip", "filtered"]), trials);
+
+    let names = c.trial_results.iter().map(|(info, _)| info.name()).collect::<Vec<_>>();
+    assert_eq!(names, ["sleeps", "ignored"]);
+    assert!(c.trial_results[0].1.duration >= std::time::Duration::from_millis(20));
+}
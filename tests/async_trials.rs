@@ -0,0 +1,128 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{atomic::{AtomicUsize, Ordering}, Arc},
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use common::{args, check, do_run_with_options, Counts};
+use libtest_mimic::{Executor, Failed, RunOptions, Trial, TrialFuture};
+
+#[macro_use]
+mod common;
+
+
+/// A future that is pending `n` times (waking itself each time) before it
+/// completes.
+struct YieldTimes(u32);
+
+impl Future for YieldTimes {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.0 == 0 {
+            return Poll::Ready(());
+        }
+        self.0 -= 1;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
+
+/// A future that never completes.
+struct Never;
+
+impl Future for Never {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<()> {
+        Poll::Pending
+    }
+}
+
+fn tests() -> Vec<Trial> {
+    vec![
+        Trial::test_async("yields", async {
+            YieldTimes(3).await;
+            Ok(())
+        }),
+        Trial::test_async("fails", async {
+            YieldTimes(1).await;
+            Err("async nope".into())
+        }),
+        Trial::test_async("hangs", async {
+            Never.await;
+            Ok(())
+        }).with_timeout(Duration::from_millis(50)),
+        Trial::test("sync", || Ok(())),
+    ]
+}
+
+#[test]
+fn built_in_executor() {
+    check(args([]), tests, 4,
+        Counts {
+            num_filtered_out: 0,
+            num_passed: 2,
+            num_failed: 1,
+            num_ignored: 0,
+            num_measured: 0,
+            num_timed_out: 1,
+            num_sharded_out: 0,
+            num_not_run: 0,
+            num_flaky: 0,
+            setup_failed: false,
+        },
+        "
+            test yields ... ok
+            test fails  ... FAILED
+            test hangs  ... TIMEOUT
+            test sync   ... ok
+
+            failures:
+
+            ---- fails ----
+            async nope
+
+            ---- hangs ----
+            test did not finish within 50ms
+
+
+            failures:
+                fails
+                hangs
+        "
+    );
+}
+
+/// Counts the futures it drives, but otherwise fails them all.
+struct Counting(Arc<AtomicUsize>);
+
+impl Executor for Counting {
+    fn block_on(&self, _: TrialFuture) -> Result<(), Failed> {
+        self.0.fetch_add(1, Ordering::SeqCst);
+        Err("not driven".into())
+    }
+}
+
+#[test]
+fn custom_executor() {
+    let count = Arc::new(AtomicUsize::new(0));
+    let options = RunOptions::new().with_executor(Counting(count.clone()));
+    let (c, _) = do_run_with_options(args(["--skip", "hangs"]), tests(), options);
+
+    assert_eq!(count.load(Ordering::SeqCst), 2);
+    assert_eq!(Counts::from(&c), Counts {
+        num_filtered_out: 1,
+        num_passed: 1,
+        num_failed: 2,
+        num_ignored: 0,
+        num_measured: 0,
+        num_timed_out: 0,
+        num_sharded_out: 0,
+        num_not_run: 0,
+        num_flaky: 0,
+        setup_failed: false,
+    });
+}
@@ -1,7 +1,17 @@
-use std::{path::Path, iter::repeat_with, collections::HashMap};
+// Not every test uses every helper.
+#![allow(dead_code)]
+
+use std::{
+    path::{Path, PathBuf},
+    iter::repeat_with,
+    collections::HashMap,
+    sync::{atomic::{AtomicUsize, Ordering}, Arc},
+    thread,
+    time::Duration,
+};
 use pretty_assertions::assert_eq;
 
-use libtest_mimic::{run, Arguments, Conclusion, Trial};
+use libtest_mimic::{run_with_options, Arguments, Conclusion, RunOptions, Trial};
 
 
 const TEMPDIR: &str = env!("CARGO_TARGET_TMPDIR");
@@ -12,19 +22,67 @@ pub fn args<const N: usize>(args: [&str; N]) -> Arguments {
     Arguments::from_iter(v)
 }
 
-pub fn do_run(mut args: Arguments, tests: Vec<Trial>) -> (Conclusion, String) {
+/// Returns a path for a file called `name` in the directory `dir` inside the
+/// temporary directory. The file does not exist (yet).
+pub fn fresh_tmp_path(dir: &str, name: &str) -> PathBuf {
+    let path = Path::new(TEMPDIR).join(dir).join(name);
+    let _ = std::fs::remove_file(&path);
+    path
+}
+
+pub fn do_run(args: Arguments, tests: Vec<Trial>) -> (Conclusion, String) {
+    do_run_with_options(args, tests, RunOptions::new())
+}
+
+pub fn do_run_with_options(
+    mut args: Arguments,
+    tests: Vec<Trial>,
+    options: RunOptions<'_>,
+) -> (Conclusion, String) {
     // Create path to temporary file.
     let suffix = repeat_with(fastrand::alphanumeric).take(10).collect::<String>();
     let path = Path::new(&TEMPDIR).join(format!("libtest_mimic_output_{suffix}.txt"));
 
     args.logfile = Some(path.display().to_string());
 
-    let c = run(&args, tests);
+    let c = run_with_options(&args, tests, options);
     let output = std::fs::read_to_string(&path)
         .expect("Can't read temporary logfile");
     std::fs::remove_file(&path)
         .expect("Can't remove temporary logfile");
-    (c, output)
+    (c, strip_backtraces(&output))
+}
+
+/// Removes backtraces of panics from the log, as those are only captured if
+/// `RUST_BACKTRACE` is set.
+pub fn strip_backtraces(log: &str) -> String {
+    // Pretty: from "stack backtrace:" to the next empty line.
+    let mut out = String::new();
+    let mut in_backtrace = false;
+    for line in log.split_inclusive('\n') {
+        if line.starts_with("stack backtrace:") {
+            in_backtrace = true;
+        } else if line.trim().is_empty() {
+            in_backtrace = false;
+        }
+        if !in_backtrace {
+            out.push_str(line);
+        }
+    }
+
+    // JSON: the escaped string value of the "backtrace" key.
+    const KEY: &str = ", \"backtrace\": \"";
+    while let Some(start) = out.find(KEY) {
+        let value = &out[start + KEY.len()..];
+        let mut escaped = false;
+        let len = value.find(|c| {
+            let end = c == '"' && !escaped;
+            escaped = c == '\\' && !escaped;
+            end
+        }).expect("unterminated backtrace string");
+        out.replace_range(start..start + KEY.len() + len + 1, "");
+    }
+    out
 }
 
 /// Removes shared indentation so that at least one line has no indentation
@@ -81,12 +139,12 @@ pub fn assert_reordered_log(actual: &str, num: u64, expected_lines: &[&str], tai
 
 /// Like `assert_eq`, but cleans the expected string (removes indendation). Also
 /// normalizes the "finished in" time if `$expected` ends with "finished in
-/// 0.00s".
+/// 0.00s" and all JSON `"exec_time"` values.
 #[macro_export]
 macro_rules! assert_log {
     ($actual:expr, $expected:expr) => {
         let mut actual = $actual.trim().to_owned();
-        let expected = crate::common::clean_expected_log($expected);
+        let expected = $crate::common::clean_expected_log($expected);
         let expected = expected.trim();
 
         if expected.ends_with("finished in 0.00s") {
@@ -97,20 +155,59 @@ macro_rules! assert_log {
             }
         }
 
-        if let Some(pos) = actual.rfind("\"exec_time\":") {
-            actual.truncate(pos);
-            actual.push_str("\"exec_time\": 0.000000000 }");
+        // Replace all times, as those are not deterministic.
+        let mut rest = std::mem::take(&mut actual);
+        while let Some(pos) = rest.find("\"exec_time\": ") {
+            let (before, after) = rest.split_at(pos + "\"exec_time\": ".len());
+            actual.push_str(before);
+            actual.push_str("0.000000000");
+            let end = after.find([',', ' ']).unwrap_or(after.len());
+            rest = after[end..].to_owned();
         }
+        actual.push_str(&rest);
 
         assert_eq!(actual, expected);
     };
 }
 
+/// The counts of a [`Conclusion`], which cannot be constructed outside of
+/// `libtest_mimic`, to compare against.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Counts {
+    pub num_filtered_out: u64,
+    pub num_passed: u64,
+    pub num_failed: u64,
+    pub num_ignored: u64,
+    pub num_measured: u64,
+    pub num_timed_out: u64,
+    pub num_sharded_out: u64,
+    pub num_not_run: u64,
+    pub num_flaky: u64,
+    pub setup_failed: bool,
+}
+
+impl From<&Conclusion> for Counts {
+    fn from(c: &Conclusion) -> Self {
+        Self {
+            num_filtered_out: c.num_filtered_out,
+            num_passed: c.num_passed,
+            num_failed: c.num_failed,
+            num_ignored: c.num_ignored,
+            num_measured: c.num_measured,
+            num_timed_out: c.num_timed_out,
+            num_sharded_out: c.num_sharded_out,
+            num_not_run: c.num_not_run,
+            num_flaky: c.num_flaky,
+            setup_failed: c.setup_failed,
+        }
+    }
+}
+
 pub fn check(
     mut args: Arguments,
     mut tests: impl FnMut() -> Vec<Trial>,
     num_running_tests: u64,
-    expected_conclusion: Conclusion,
+    expected_conclusion: Counts,
     expected_output: &str,
 ) {
     // Run in single threaded mode
@@ -122,7 +219,7 @@ pub fn check(
         lines[..lines.len() - 1].join("\n")
     };
     assert_eq!(actual.trim(), expected.trim());
-    assert_eq!(c, expected_conclusion);
+    assert_eq!(Counts::from(&c), expected_conclusion);
 
     // Run in multithreaded mode.
     let (c, out) = do_run(args, tests());
@@ -132,18 +229,92 @@ pub fn check(
         &expected_output.lines().collect::<Vec<_>>(),
         &conclusion_to_output(&c),
     );
-    assert_eq!(c, expected_conclusion);
+    assert_eq!(Counts::from(&c), expected_conclusion);
 }
 
 fn conclusion_to_output(c: &Conclusion) -> String {
-    let Conclusion { num_filtered_out, num_passed, num_failed, num_ignored, num_measured } = *c;
+    let Conclusion {
+        num_filtered_out,
+        num_passed,
+        num_failed,
+        num_ignored,
+        num_measured,
+        num_timed_out,
+        num_sharded_out,
+        num_not_run,
+        num_flaky,
+        setup_failed,
+        ..
+    } = *c;
+    let timed_out = match num_timed_out {
+        0 => String::new(),
+        n => format!(" {n} timed out;"),
+    };
+    let sharded_out = match num_sharded_out {
+        0 => String::new(),
+        n => format!(" {n} sharded out;"),
+    };
+    let flaky = match num_flaky {
+        0 => String::new(),
+        n => format!(" {n} flaky;"),
+    };
+    let not_run = match num_not_run {
+        0 => String::new(),
+        n => format!(" {n} not run;"),
+    };
+    let setup_failed = if setup_failed { " setup failed;" } else { "" };
     format!(
-        "test result: {}. {} passed; {} failed; {} ignored; {} measured; {} filtered out;",
-        if num_failed > 0 { "FAILED" } else { "ok" },
+        "test result: {}. {} passed; {} failed;{}{} {} ignored; {} measured; {} filtered out;{}{}{}",
+        if c.has_failed() { "FAILED" } else { "ok" },
         num_passed,
         num_failed,
+        timed_out,
+        flaky,
         num_ignored,
         num_measured,
         num_filtered_out,
+        sharded_out,
+        not_run,
+        setup_failed,
     )
 }
+
+/// Counts the threads used by running trials (by weight) and remembers the
+/// maximum.
+#[derive(Default)]
+pub struct Concurrency {
+    running: AtomicUsize,
+    max: AtomicUsize,
+}
+
+impl Concurrency {
+    /// Returns a trial with the given weight that is counted while it sleeps
+    /// for a bit.
+    pub fn trial(self: &Arc<Self>, name: &str, weight: usize) -> Trial {
+        let this = self.clone();
+        Trial::test(name, move || {
+            this.start(weight);
+            thread::sleep(Duration::from_millis(50));
+            this.finish(weight);
+            Ok(())
+        })
+        .with_weight(weight)
+    }
+
+    pub fn start(&self, weight: usize) {
+        let running = self.running.fetch_add(weight, Ordering::SeqCst) + weight;
+        self.max.fetch_max(running, Ordering::SeqCst);
+    }
+
+    pub fn finish(&self, weight: usize) {
+        self.running.fetch_sub(weight, Ordering::SeqCst);
+    }
+
+    pub fn running(&self) -> usize {
+        self.running.load(Ordering::SeqCst)
+    }
+
+    pub fn max(&self) -> usize {
+        self.max.load(Ordering::SeqCst)
+    }
+}
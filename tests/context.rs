@@ -0,0 +1,100 @@
+use std::{
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use common::{args, do_run};
+use libtest_mimic::{Failed, Trial};
+use pretty_assertions::assert_eq;
+
+#[macro_use]
+mod common;
+
+
+/// Name, kind, seed and temp dir of each trial that ran.
+type Seen = Arc<Mutex<Vec<(String, String, u64, PathBuf)>>>;
+
+fn tests(seen: &Seen) -> Vec<Trial> {
+    let trial = |name: &str, result: fn() -> Result<(), Failed>| {
+        let seen = seen.clone();
+        Trial::test_with_context(name, move |ctx| {
+            std::fs::write(ctx.temp_dir().join("file.txt"), "hi").unwrap();
+            ctx.log(format_args!("seed is {}", ctx.seed()));
+            seen.lock().unwrap().push((
+                ctx.name().to_owned(),
+                ctx.kind().to_owned(),
+                ctx.seed(),
+                ctx.temp_dir().to_owned(),
+            ));
+            result()
+        })
+    };
+
+    vec![
+        trial("passes", || Ok(())).with_kind("ctx"),
+        trial("fails", || Err("nope".into())),
+    ]
+}
+
+fn run(args: libtest_mimic::Arguments) -> (Vec<(String, String, u64, PathBuf)>, String) {
+    let seen = Seen::default();
+    let (_, out) = do_run(args, tests(&seen));
+    let mut seen = seen.lock().unwrap().clone();
+    seen.sort();
+    (seen, out)
+}
+
+#[test]
+fn context() {
+    let (seen, out) = run(args(["--test-threads", "1"]));
+    let [fails, passes] = &seen[..] else {
+        panic!("unexpected trials: {seen:?}");
+    };
+    let (fails_name, fails_kind, fails_seed, fails_dir) = fails;
+    let (passes_name, passes_kind, passes_seed, passes_dir) = passes;
+
+    assert_eq!((fails_name.as_str(), fails_kind.as_str()), ("fails", ""));
+    assert_eq!((passes_name.as_str(), passes_kind.as_str()), ("passes", "ctx"));
+    assert_ne!(fails_seed, passes_seed);
+
+    // The temporary directory is only kept on failure.
+    assert!(!passes_dir.exists());
+    assert!(fails_dir.join("file.txt").exists());
+    std::fs::remove_dir_all(fails_dir).unwrap();
+
+    assert_log!(out, &format!("
+        running 2 tests
+        test [ctx] passes ... ok
+        test       fails  ... FAILED
+
+        failures:
+
+        ---- fails stdout ----
+        seed is {fails_seed}
+        nope
+        note: temporary directory kept at {}
+
+
+        failures:
+            fails
+
+        test result: FAILED. 1 passed; 1 failed; 0 ignored; 0 measured; 0 filtered out; \
+            finished in 0.00s
+    ", fails_dir.display()));
+}
+
+#[test]
+fn seeds_depend_on_run_seed() {
+    let seeds = |args| {
+        let (seen, _) = run(args);
+        for (_, _, _, dir) in &seen {
+            let _ = std::fs::remove_dir_all(dir);
+        }
+        seen.into_iter().map(|(_, _, seed, _)| seed).collect::<Vec<_>>()
+    };
+
+    let default = seeds(args([]));
+    assert_eq!(default, seeds(args(["--test-threads", "1"])));
+    assert_eq!(seeds(args(["--shuffle-seed", "7"])), seeds(args(["--shuffle-seed", "7"])));
+    assert_ne!(default, seeds(args(["--shuffle-seed", "7"])));
+}
@@ -0,0 +1,139 @@
+use common::{args, check, do_run, Counts};
+use libtest_mimic::{Failed, Trial};
+
+#[macro_use]
+mod common;
+
+
+fn tests() -> Vec<Trial> {
+    vec![
+        Trial::test("golden", || {
+            let expected = "one\ntwo\nthree\nfour\nfive\nsix\nseven\neight\nnine\n";
+            let actual = "one\ntwo\nthree\nfour\n5\nsix\nseven\neight\nnine\nten\n";
+            Err(Failed::from("output does not match golden file")
+                .with_expected(expected)
+                .with_actual(actual)
+                .with_location("tests/golden/numbers.txt", 5))
+        }),
+        Trial::test("context", || {
+            Err(Failed::from("wrong answer")
+                .with_actual("41")
+                .with_note("the answer is always 42")
+                .with_context("input", "question.txt"))
+        }),
+    ]
+}
+
+#[test]
+fn pretty() {
+    check(args([]), tests, 2,
+        Counts {
+            num_filtered_out: 0,
+            num_passed: 0,
+            num_failed: 2,
+            num_ignored: 0,
+            num_measured: 0,
+            num_timed_out: 0,
+            num_sharded_out: 0,
+            num_not_run: 0,
+            num_flaky: 0,
+            setup_failed: false,
+        },
+        "
+            test golden  ... FAILED
+            test context ... FAILED
+
+            failures:
+
+            ---- golden ----
+            output does not match golden file
+              --> tests/golden/numbers.txt:5
+
+            diff (-expected +actual):
+            @@ -2,8 +2,9 @@
+             two
+             three
+             four
+            -five
+            +5
+             six
+             seven
+             eight
+             nine
+            +ten
+
+            ---- context ----
+            wrong answer
+            note: the answer is always 42
+            input: question.txt
+            actual:
+            41
+
+
+            failures:
+                golden
+                context
+        "
+    );
+}
+
+#[test]
+fn json() {
+    let (_, out) = do_run(args(["--format", "json", "--test-threads", "1", "context"]), tests());
+    assert_log!(out, r#"
+        { "type": "suite", "event": "started", "test_count": 1 }
+        { "type": "test", "event": "started", "name": "context" }
+        { "type": "test", "name": "context", "event": "failed", "exec_time": 0.000000000, "stdout": "Error: \"wrong answer\"\n", "actual": "41", "notes": ["the answer is always 42"], "context": { "input": "question.txt" } }
+        { "type": "suite", "event": "failed", "passed": 0, "failed": 1, "ignored": 0, "measured": 0, "filtered_out": 1, "exec_time": 0.000000000 }
+    "#);
+}
+
+#[test]
+fn invisible_differences() {
+    let mismatch = |name: &str, expected: &'static str, actual: &'static str| {
+        Trial::test(name, move || {
+            Err(Failed::from("mismatch").with_expected(expected).with_actual(actual))
+        })
+    };
+    let tests = vec![
+        mismatch("newline", "a\nb\n", "a\nb"),
+        mismatch("crlf", "a\nb\n", "a\r\nb\r\n"),
+        mismatch("equal", "a", "a"),
+    ];
+    let (_, out) = do_run(args(["--test-threads", "1"]), tests);
+    assert_log!(out, "
+        running 3 tests
+        test newline ... FAILED
+        test crlf    ... FAILED
+        test equal   ... FAILED
+
+        failures:
+
+        ---- newline ----
+        mismatch
+
+        diff (-expected +actual):
+        (expected ends with a newline, actual does not)
+
+        ---- crlf ----
+        mismatch
+
+        diff (-expected +actual):
+        (only the line endings differ (`\\r\\n` vs. `\\n`))
+
+        ---- equal ----
+        mismatch
+
+        diff (-expected +actual):
+        (expected and actual are equal)
+
+
+        failures:
+            newline
+            crlf
+            equal
+
+        test result: FAILED. 0 passed; 3 failed; 0 ignored; 0 measured; 0 filtered out; \
+            finished in 0.00s
+    ");
+}
@@ -0,0 +1,215 @@
+use std::{
+    panic::{self, AssertUnwindSafe},
+    sync::Mutex,
+    time::Duration,
+};
+
+use common::{args, do_run_with_options, Counts};
+use libtest_mimic::{
+    run_with_options, Conclusion, Failed, Reporter, RunOptions, TestInfo, Trial, TrialResult,
+};
+
+#[macro_use]
+mod common;
+
+
+fn tests() -> Vec<Trial> {
+    vec![
+        Trial::test("cat", || Ok(())),
+        Trial::test("dog", || Err("woof".into())),
+        Trial::test("fox", || Ok(())),
+    ]
+}
+
+#[test]
+fn order() {
+    let events = Mutex::new(Vec::new());
+    let log = |event: String| events.lock().unwrap().push(event);
+    let options = RunOptions::new()
+        .before_all(|| {
+            log("before_all".into());
+            Ok(())
+        })
+        .after_all(|| log("after_all".into()))
+        .before_each(|info| {
+            log(format!("before {}", info.name()));
+            Ok(())
+        })
+        .after_each(|info| {
+            log(format!("after {}", info.name()));
+            Ok(())
+        });
+    let (c, _) = do_run_with_options(args(["--test-threads", "1"]), tests(), options);
+
+    assert_eq!(c.num_passed, 2);
+    assert_eq!(c.num_failed, 1);
+    assert_eq!(events.into_inner().unwrap(), [
+        "before_all",
+        "before cat",
+        "after cat",
+        "before dog",
+        "after dog",
+        "before fox",
+        "after fox",
+        "after_all",
+    ]);
+}
+
+#[test]
+fn trial_hooks_fail() {
+    let options = RunOptions::new()
+        .before_each(|info| match info.name() {
+            "cat" => Err("no cat food".into()),
+            _ => Ok(()),
+        })
+        .after_each(|info| match info.name() {
+            "fox" => Err("fox left a mess".into()),
+            _ => Ok(()),
+        });
+    let (c, out) = do_run_with_options(args(["--test-threads", "1"]), tests(), options);
+
+    assert_eq!(Counts::from(&c), Counts {
+        num_filtered_out: 0,
+        num_passed: 0,
+        num_failed: 3,
+        num_ignored: 0,
+        num_measured: 0,
+        num_timed_out: 0,
+        num_sharded_out: 0,
+        num_not_run: 0,
+        num_flaky: 0,
+        setup_failed: false,
+    });
+    assert_log!(out, "
+        running 3 tests
+        test cat ... FAILED
+        test dog ... FAILED
+        test fox ... FAILED
+
+        failures:
+
+        ---- cat ----
+        no cat food
+
+        ---- dog ----
+        woof
+
+        ---- fox ----
+        fox left a mess
+
+
+        failures:
+            cat
+            dog
+            fox
+
+        test result: FAILED. 0 passed; 3 failed; 0 ignored; 0 measured; 0 filtered out; \
+            finished in 0.00s
+    ");
+}
+
+fn failing_setup<'a>(torn_down: &'a Mutex<bool>) -> RunOptions<'a> {
+    RunOptions::new()
+        .before_all(|| Err(Failed::from("database did not start").with_note("port in use")))
+        .after_all(|| *torn_down.lock().unwrap() = true)
+        .before_each(|_| panic!("no trial should run"))
+}
+
+#[test]
+fn setup_fails() {
+    let torn_down = Mutex::new(false);
+    let (c, out) = do_run_with_options(args([]), tests(), failing_setup(&torn_down));
+
+    assert!(*torn_down.lock().unwrap());
+    assert_eq!(Counts::from(&c), Counts {
+        num_filtered_out: 0,
+        num_passed: 0,
+        num_failed: 0,
+        num_ignored: 0,
+        num_measured: 0,
+        num_timed_out: 0,
+        num_sharded_out: 0,
+        num_not_run: 3,
+        num_flaky: 0,
+        setup_failed: true,
+    });
+    assert_log!(out, "
+        running 3 tests
+
+        setup failed: database did not start
+        note: port in use
+
+        test result: FAILED. 0 passed; 0 failed; 0 ignored; 0 measured; 0 filtered out; \
+            3 not run; setup failed; finished in 0.00s
+    ");
+}
+
+#[test]
+fn setup_panics() {
+    let options = RunOptions::new().before_all(|| panic!("kaputt"));
+    let (c, out) = do_run_with_options(args([]), tests(), options);
+
+    assert!(c.setup_failed);
+    assert!(out.contains("setup failed: setup panicked: kaputt\n"));
+}
+
+#[test]
+fn setup_fails_json() {
+    let torn_down = Mutex::new(false);
+    let args = args(["--format", "json"]);
+    let (_, out) = do_run_with_options(args, tests(), failing_setup(&torn_down));
+
+    assert_log!(out, r#"
+        { "type": "suite", "event": "started", "test_count": 3 }
+        { "type": "suite", "event": "setup_failed", "message": "database did not start", "notes": ["port in use"] }
+        { "type": "suite", "event": "failed", "passed": 0, "failed": 0, "ignored": 0, "measured": 0, "filtered_out": 0, "not_run": 3, "setup_failed": true, "exec_time": 0.000000000 }
+    "#);
+}
+
+#[test]
+fn trial_hooks_panic() {
+    for threads in ["1", "2"] {
+        let torn_down = Mutex::new(false);
+        let options = RunOptions::new()
+            .before_each(|info| match info.name() {
+                "cat" => panic!("no cat food"),
+                _ => Ok(()),
+            })
+            .after_each(|info| match info.name() {
+                "fox" => panic!("fox left a mess"),
+                _ => Ok(()),
+            })
+            .after_all(|| *torn_down.lock().unwrap() = true);
+        let (c, out) = do_run_with_options(args(["--test-threads", threads]), tests(), options);
+
+        assert!(*torn_down.lock().unwrap());
+        assert_eq!(c.num_failed, 3);
+        assert!(out.contains("---- cat ----\nsetup panicked: no cat food\n"), "{out}");
+        assert!(out.contains("---- fox ----\nteardown panicked: fox left a mess\n"), "{out}");
+        assert!(out.contains("test result: FAILED."), "{out}");
+    }
+}
+
+#[test]
+fn teardown_after_unwinding() {
+    struct PanickingReporter;
+
+    impl Reporter for PanickingReporter {
+        fn trial_finished(&mut self, _: &TestInfo, _: &TrialResult) {
+            panic!("reporter broke");
+        }
+
+        fn summary(&mut self, _: &Conclusion, _: Duration) {}
+    }
+
+    let torn_down = Mutex::new(false);
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        let options = RunOptions::new()
+            .with_reporter(PanickingReporter)
+            .after_all(|| *torn_down.lock().unwrap() = true);
+        let _ = run_with_options(&args(["--test-threads", "1"]), tests(), options);
+    }));
+
+    assert!(result.is_err());
+    assert!(*torn_down.lock().unwrap());
+}
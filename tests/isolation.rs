@@ -0,0 +1,321 @@
+//! This test uses `libtest-mimic` as its own harness, since `--isolate`
+//! re-executes the current binary: the checks below run trials with
+//! `--isolate`, which spawn this binary again with `--isolated-child`.
+
+use std::time::Duration;
+
+use common::{args, check, do_run, Counts};
+use libtest_mimic::{Arguments, Measurement, Trial};
+
+#[macro_use]
+mod common;
+
+
+fn main() {
+    let args = Arguments::from_args();
+    if args.isolated_child {
+        let conclusion = libtest_mimic::run(&args, isolated_trials());
+        if args.filter.as_deref() == Some("prints_after_run") {
+            println!("printed after run");
+        }
+        conclusion.exit();
+    }
+
+    let checks = vec![
+        Trial::test("outcomes_are_reported", outcomes_are_reported),
+        Trial::test("crashes_are_reported", crashes_are_reported)
+            .with_ignored_flag(cfg!(not(unix))),
+        Trial::test("timeout_kills_child", timeout_kills_child),
+        Trial::test("output_is_captured", output_is_captured),
+        Trial::test("show_output", show_output),
+        Trial::test("crashes_are_retried", crashes_are_retried),
+        Trial::test("failure_details", failure_details),
+        Trial::test("output_after_run", output_after_run),
+    ];
+    libtest_mimic::run(&args, checks).exit();
+}
+
+fn isolated_trials() -> Vec<Trial> {
+    vec![
+        Trial::test("passes", || Ok(())),
+        Trial::test("fails", || Err("nope".into())),
+        Trial::test("panics", || panic!("oh no")),
+        Trial::test("exits", || std::process::exit(3)),
+        Trial::test("aborts", || std::process::abort()),
+        Trial::test("hangs", || {
+            std::thread::sleep(Duration::from_secs(60));
+            Ok(())
+        }),
+        Trial::bench("measures", |_| Ok(Some(Measurement { avg: 7, variance: 1 })))
+            .with_kind("fast"),
+        Trial::test("chatty_fail", || {
+            eprintln!("to stderr");
+            Err("nope".into())
+        }),
+        Trial::test("chatty_pass", || {
+            print!("no newline");
+            Ok(())
+        }),
+        Trial::test("fails_with_details", || {
+            Err(libtest_mimic::Failed::from("mismatch")
+                .with_expected("a\tb\n")
+                .with_actual("a b\n")
+                .with_location("src/main.rs", 3)
+                .with_note("tabs matter")
+                .with_context("key", "value"))
+        }),
+        // The harness prints something after `run` returned (see `main`).
+        Trial::test("prints_after_run", || Ok(())),
+        Trial::test("aborts_once", || {
+            // Each attempt runs in a new process, so remember the first
+            // attempt on disk.
+            let marker = aborts_once_marker();
+            if !marker.exists() {
+                std::fs::write(&marker, "").unwrap();
+                std::process::abort();
+            }
+            Ok(())
+        }),
+    ]
+}
+
+fn outcomes_are_reported() -> Result<(), libtest_mimic::Failed> {
+    let args = args(["--isolate", "--nocapture", "--skip", "aborts", "--skip", "hangs", "--skip", "chatty", "--skip", "details", "--skip", "after_run"]);
+    check(args, isolated_trials, 5,
+        Counts {
+            num_filtered_out: 7,
+            num_passed: 2,
+            num_failed: 3,
+            num_ignored: 0,
+            num_measured: 0,
+            num_timed_out: 0,
+            num_sharded_out: 0,
+            num_not_run: 0,
+            num_flaky: 0,
+            setup_failed: false,
+        },
+        "
+            test        passes   ... ok
+            test        fails    ... FAILED
+            test        panics   ... FAILED
+            test        exits    ... FAILED
+            test [fast] measures ... ok
+
+            failures:
+
+            ---- fails ----
+            nope
+
+            ---- panics ----
+            test panicked: oh no
+              --> tests/isolation.rs:42
+
+            ---- exits ----
+            test process exited with code 3 without reporting an outcome
+
+
+            failures:
+                fails
+                panics
+                exits
+        ",
+    );
+    Ok(())
+}
+
+fn crashes_are_reported() -> Result<(), libtest_mimic::Failed> {
+    check(args(["--isolate", "--exact", "aborts"]), isolated_trials, 1,
+        Counts {
+            num_filtered_out: 11,
+            num_passed: 0,
+            num_failed: 1,
+            num_ignored: 0,
+            num_measured: 0,
+            num_timed_out: 0,
+            num_sharded_out: 0,
+            num_not_run: 0,
+            num_flaky: 0,
+            setup_failed: false,
+        },
+        "
+            test aborts ... FAILED
+
+            failures:
+
+            ---- aborts stdout ----
+            test process was terminated by signal 6 (SIGABRT)
+
+
+            failures:
+                aborts
+        ",
+    );
+    Ok(())
+}
+
+fn timeout_kills_child() -> Result<(), libtest_mimic::Failed> {
+    check(args(["--isolate", "--timeout", "0.5", "--exact", "hangs"]), isolated_trials, 1,
+        Counts {
+            num_filtered_out: 11,
+            num_passed: 0,
+            num_failed: 0,
+            num_ignored: 0,
+            num_measured: 0,
+            num_timed_out: 1,
+            num_sharded_out: 0,
+            num_not_run: 0,
+            num_flaky: 0,
+            setup_failed: false,
+        },
+        "
+            test hangs ... TIMEOUT
+
+            failures:
+
+            ---- hangs stdout ----
+            test did not finish within 500ms
+
+
+            failures:
+                hangs
+        ",
+    );
+    Ok(())
+}
+
+fn output_is_captured() -> Result<(), libtest_mimic::Failed> {
+    check(args(["--isolate", "chatty"]), isolated_trials, 2,
+        Counts {
+            num_filtered_out: 10,
+            num_passed: 1,
+            num_failed: 1,
+            num_ignored: 0,
+            num_measured: 0,
+            num_timed_out: 0,
+            num_sharded_out: 0,
+            num_not_run: 0,
+            num_flaky: 0,
+            setup_failed: false,
+        },
+        "
+            test chatty_fail ... FAILED
+            test chatty_pass ... ok
+
+            failures:
+
+            ---- chatty_fail stdout ----
+            to stderr
+            nope
+
+
+            failures:
+                chatty_fail
+        ",
+    );
+    Ok(())
+}
+
+fn show_output() -> Result<(), libtest_mimic::Failed> {
+    let (c, out) = do_run(args(["--isolate", "--show-output", "--exact", "chatty_pass"]), isolated_trials());
+    assert_eq!(c.num_passed, 1);
+    assert_log!(out, "
+        running 1 test
+        test chatty_pass ... ok
+
+        successes:
+
+        ---- chatty_pass stdout ----
+        no newline
+
+        successes:
+            chatty_pass
+
+        test result: ok. 1 passed; 0 failed; 0 ignored; 0 measured; 11 filtered out; \
+            finished in 0.00s
+    ");
+    Ok(())
+}
+
+fn output_after_run() -> Result<(), libtest_mimic::Failed> {
+    let (c, out) = do_run(
+        args(["--isolate", "--show-output", "--exact", "prints_after_run"]),
+        isolated_trials(),
+    );
+    assert_eq!(c.num_passed, 1);
+    assert_log!(out, "
+        running 1 test
+        test prints_after_run ... ok
+
+        successes:
+
+        ---- prints_after_run stdout ----
+        printed after run
+
+
+        successes:
+            prints_after_run
+
+        test result: ok. 1 passed; 0 failed; 0 ignored; 0 measured; 11 filtered out; \
+            finished in 0.00s
+    ");
+    Ok(())
+}
+
+fn aborts_once_marker() -> std::path::PathBuf {
+    std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join("libtest_mimic_aborts_once")
+}
+
+fn crashes_are_retried() -> Result<(), libtest_mimic::Failed> {
+    let _ = std::fs::remove_file(aborts_once_marker());
+    let (c, out) = do_run(
+        args(["--isolate", "--retries", "1", "--exact", "aborts_once"]),
+        isolated_trials(),
+    );
+    assert_eq!(c.num_flaky, 1);
+    assert_log!(out, "
+        running 1 test
+        test aborts_once ... FLAKY
+
+        test result: ok. 0 passed; 0 failed; 1 flaky; 0 ignored; 0 measured; 11 filtered out; \
+            finished in 0.00s
+    ");
+    Ok(())
+}
+
+fn failure_details() -> Result<(), libtest_mimic::Failed> {
+    check(args(["--isolate", "--exact", "fails_with_details"]), isolated_trials, 1,
+        Counts {
+            num_filtered_out: 11,
+            num_passed: 0,
+            num_failed: 1,
+            num_ignored: 0,
+            num_measured: 0,
+            num_timed_out: 0,
+            num_sharded_out: 0,
+            num_not_run: 0,
+            num_flaky: 0,
+            setup_failed: false,
+        },
+        "
+            test fails_with_details ... FAILED
+
+            failures:
+
+            ---- fails_with_details stdout ----
+            mismatch
+              --> src/main.rs:3
+            note: tabs matter
+            key: value
+
+            diff (-expected +actual):
+            @@ -1,1 +1,1 @@
+            -a\tb
+            +a b
+
+
+            failures:
+                fails_with_details
+        ",
+    );
+    Ok(())
+}
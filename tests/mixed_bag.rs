@@ -1,5 +1,7 @@
-use crate::common::{args, check, do_run};
-use libtest_mimic::{Conclusion, Measurement, Trial};
+use std::path::Path;
+
+use crate::common::{args, check, do_run, Counts};
+use libtest_mimic::{Measurement, Shard, Trial};
 use pretty_assertions::assert_eq;
 
 #[macro_use]
@@ -36,12 +38,17 @@ fn tests() -> Vec<Trial> {
 #[test]
 fn normal() {
     check(args([]), tests, 17,
-        Conclusion {
+        Counts {
             num_filtered_out: 0,
             num_passed: 4,
             num_failed: 5,
             num_ignored: 8,
             num_measured: 0,
+            num_timed_out: 0,
+            num_sharded_out: 0,
+            num_not_run: 0,
+            num_flaky: 0,
+            setup_failed: false,
         },
         "
             test          cat    ... ok
@@ -93,12 +100,17 @@ fn normal() {
 #[test]
 fn test_mode() {
     check(args(["--test"]), tests, 17,
-        Conclusion {
+        Counts {
             num_filtered_out: 0,
             num_passed: 2,
             num_failed: 3,
             num_ignored: 12,
             num_measured: 0,
+            num_timed_out: 0,
+            num_sharded_out: 0,
+            num_not_run: 0,
+            num_flaky: 0,
+            setup_failed: false,
         },
         "
             test          cat    ... ok
@@ -142,12 +154,17 @@ fn test_mode() {
 #[test]
 fn bench_mode() {
     check(args(["--bench"]), tests, 17,
-        Conclusion {
+        Counts {
             num_filtered_out: 0,
             num_passed: 0,
             num_failed: 2,
             num_ignored: 13,
             num_measured: 2,
+            num_timed_out: 0,
+            num_sharded_out: 0,
+            num_not_run: 0,
+            num_flaky: 0,
+            setup_failed: false,
         },
         "
             test          cat    ... ignored
@@ -206,12 +223,17 @@ fn list() {
         [banana] orange: bench
         [banana] pink: bench
     ");
-    assert_eq!(c, Conclusion {
+    assert_eq!(Counts::from(&c), Counts {
         num_filtered_out: 0,
         num_passed: 0,
         num_failed: 0,
         num_ignored: 0,
         num_measured: 0,
+        num_timed_out: 0,
+        num_sharded_out: 0,
+        num_not_run: 0,
+        num_flaky: 0,
+        setup_failed: false,
     });
 }
 
@@ -228,12 +250,17 @@ fn list_ignored() {
         [banana] orange: bench
         [banana] pink: bench
     ");
-    assert_eq!(c, Conclusion {
+    assert_eq!(Counts::from(&c), Counts {
         num_filtered_out: 0,
         num_passed: 0,
         num_failed: 0,
         num_ignored: 0,
         num_measured: 0,
+        num_timed_out: 0,
+        num_sharded_out: 0,
+        num_not_run: 0,
+        num_flaky: 0,
+        setup_failed: false,
     });
 }
 
@@ -251,12 +278,17 @@ fn list_with_filter() {
         [banana] orange: bench
         [banana] pink: bench
     ");
-    assert_eq!(c, Conclusion {
+    assert_eq!(Counts::from(&c), Counts {
         num_filtered_out: 0,
         num_passed: 0,
         num_failed: 0,
         num_ignored: 0,
         num_measured: 0,
+        num_timed_out: 0,
+        num_sharded_out: 0,
+        num_not_run: 0,
+        num_flaky: 0,
+        setup_failed: false,
     });
 }
 
@@ -267,23 +299,33 @@ fn list_with_filter_exact() {
     assert_log!(out, "
         [apple] fox: test
     ");
-    assert_eq!(c, Conclusion {
+    assert_eq!(Counts::from(&c), Counts {
         num_filtered_out: 0,
         num_passed: 0,
         num_failed: 0,
         num_ignored: 0,
         num_measured: 0,
+        num_timed_out: 0,
+        num_sharded_out: 0,
+        num_not_run: 0,
+        num_flaky: 0,
+        setup_failed: false,
     });
     let (c, out) = common::do_run(args(["--list", "--exact", "fly"]), tests());
     assert_log!(out, "
         [banana] fly: test
     ");
-    assert_eq!(c, Conclusion {
+    assert_eq!(Counts::from(&c), Counts {
         num_filtered_out: 0,
         num_passed: 0,
         num_failed: 0,
         num_ignored: 0,
         num_measured: 0,
+        num_timed_out: 0,
+        num_sharded_out: 0,
+        num_not_run: 0,
+        num_flaky: 0,
+        setup_failed: false,
     });
 
     // --skip --exact can be used to exclude tests.
@@ -315,12 +357,17 @@ fn list_with_filter_exact() {
         [banana] orange: bench
         [banana] pink: bench
     ");
-    assert_eq!(c, Conclusion {
+    assert_eq!(Counts::from(&c), Counts {
         num_filtered_out: 0,
         num_passed: 0,
         num_failed: 0,
         num_ignored: 0,
         num_measured: 0,
+        num_timed_out: 0,
+        num_sharded_out: 0,
+        num_not_run: 0,
+        num_flaky: 0,
+        setup_failed: false,
     });
 
     // --skip --exact matches test names without the kind as well.
@@ -329,12 +376,17 @@ fn list_with_filter_exact() {
 #[test]
 fn filter_c() {
     check(args(["c"]), tests, 2,
-        Conclusion {
+        Counts {
             num_filtered_out: 15,
             num_passed: 1,
             num_failed: 0,
             num_ignored: 1,
             num_measured: 0,
+            num_timed_out: 0,
+            num_sharded_out: 0,
+            num_not_run: 0,
+            num_flaky: 0,
+            setup_failed: false,
         },
         "
             test cat  ... ok
@@ -346,12 +398,17 @@ fn filter_c() {
 #[test]
 fn filter_o_test() {
     check(args(["--test", "o"]), tests, 6,
-        Conclusion {
+        Counts {
             num_filtered_out: 11,
             num_passed: 1,
             num_failed: 1,
             num_ignored: 4,
             num_measured: 0,
+            num_timed_out: 0,
+            num_sharded_out: 0,
+            num_not_run: 0,
+            num_flaky: 0,
+            setup_failed: false,
         },
         "
             test          dog    ... FAILED
@@ -376,12 +433,17 @@ fn filter_o_test() {
 #[test]
 fn filter_o_test_include_ignored() {
     check(args(["--test", "--include-ignored", "o"]), tests, 6,
-        Conclusion {
+        Counts {
             num_filtered_out: 11,
             num_passed: 2,
             num_failed: 2,
             num_ignored: 2,
             num_measured: 0,
+            num_timed_out: 0,
+            num_sharded_out: 0,
+            num_not_run: 0,
+            num_flaky: 0,
+            setup_failed: false,
         },
         "
             test          dog    ... FAILED
@@ -410,12 +472,17 @@ fn filter_o_test_include_ignored() {
 #[test]
 fn filter_o_test_ignored() {
     check(args(["--test", "--ignored", "o"]), tests, 3,
-        Conclusion {
+        Counts {
             num_filtered_out: 14,
             num_passed: 1,
             num_failed: 1,
             num_ignored: 1,
             num_measured: 0,
+            num_timed_out: 0,
+            num_sharded_out: 0,
+            num_not_run: 0,
+            num_flaky: 0,
+            setup_failed: false,
         },
         "
             test          frog   ... ok
@@ -437,12 +504,17 @@ fn filter_o_test_ignored() {
 #[test]
 fn normal_include_ignored() {
     check(args(["--include-ignored"]), tests, 17,
-        Conclusion {
+        Counts {
             num_filtered_out: 0,
             num_passed: 8,
             num_failed: 9,
             num_ignored: 0,
             num_measured: 0,
+            num_timed_out: 0,
+            num_sharded_out: 0,
+            num_not_run: 0,
+            num_flaky: 0,
+            setup_failed: false,
         },
         "
             test          cat    ... ok
@@ -510,12 +582,17 @@ fn normal_include_ignored() {
 #[test]
 fn normal_ignored() {
     check(args(["--ignored"]), tests, 8,
-        Conclusion {
+        Counts {
             num_filtered_out: 9,
             num_passed: 4,
             num_failed: 4,
             num_ignored: 0,
             num_measured: 0,
+            num_timed_out: 0,
+            num_sharded_out: 0,
+            num_not_run: 0,
+            num_flaky: 0,
+            setup_failed: false,
         },
         "
             test          frog   ... ok
@@ -554,12 +631,17 @@ fn normal_ignored() {
 #[test]
 fn lots_of_flags() {
     check(args(["--include-ignored", "--skip", "g", "--test", "o"]), tests, 3,
-        Conclusion {
+        Counts {
             num_filtered_out: 14,
             num_passed: 1,
             num_failed: 1,
             num_ignored: 1,
             num_measured: 0,
+            num_timed_out: 0,
+            num_sharded_out: 0,
+            num_not_run: 0,
+            num_flaky: 0,
+            setup_failed: false,
         },
         "
             test [apple] fox    ... ok
@@ -581,12 +663,17 @@ fn lots_of_flags() {
 #[test]
 fn terse_output() {
     let (c, out) = do_run(args(["--format", "terse", "--test-threads", "1"]), tests());
-    assert_eq!(c, Conclusion {
+    assert_eq!(Counts::from(&c), Counts {
         num_filtered_out: 0,
         num_passed: 4,
         num_failed: 5,
         num_ignored: 8,
         num_measured: 0,
+        num_timed_out: 0,
+        num_sharded_out: 0,
+        num_not_run: 0,
+        num_flaky: 0,
+        setup_failed: false,
     });
     assert_log!(out, "
         running 17 tests
@@ -625,15 +712,320 @@ fn terse_output() {
 fn json_output() {
     let (c, out) = do_run(args(["--format", "json", "--test-threads", "1"]), tests());
     assert_eq!(
-        c,
-        Conclusion {
+        Counts::from(&c),
+        Counts {
             num_filtered_out: 0,
             num_passed: 4,
             num_failed: 5,
             num_ignored: 8,
             num_measured: 0,
+            num_timed_out: 0,
+            num_sharded_out: 0,
+            num_not_run: 0,
+            num_flaky: 0,
+            setup_failed: false,
         }
     );
 
     assert_log!(out, include_str!("json-output.json"));
 }
+
+#[test]
+fn junit_output() {
+    let (c, out) = do_run(args(["--format", "junit", "--test-threads", "1"]), tests());
+    assert_eq!(
+        Counts::from(&c),
+        Counts {
+            num_filtered_out: 0,
+            num_passed: 4,
+            num_failed: 5,
+            num_ignored: 8,
+            num_measured: 0,
+            num_timed_out: 0,
+            num_sharded_out: 0,
+            num_not_run: 0,
+            num_flaky: 0,
+            setup_failed: false,
+        }
+    );
+
+    // Replace all times, as those are not deterministic.
+    let mut normalized = String::new();
+    let mut rest = out.as_str();
+    while let Some(pos) = rest.find(" time=\"") {
+        let (before, after) = rest.split_at(pos + " time=\"".len());
+        normalized.push_str(before);
+        normalized.push_str("0.000");
+        rest = &after[after.find('"').unwrap()..];
+    }
+    normalized.push_str(rest);
+
+    assert_log!(normalized, include_str!("junit-output.xml"));
+}
+
+#[test]
+fn tap_output() {
+    let (c, out) = do_run(args(["--format", "tap", "--bench", "--test-threads", "1"]), tests());
+    assert_eq!(
+        Counts::from(&c),
+        Counts {
+            num_filtered_out: 0,
+            num_passed: 0,
+            num_failed: 2,
+            num_ignored: 13,
+            num_measured: 2,
+            num_timed_out: 0,
+            num_sharded_out: 0,
+            num_not_run: 0,
+            num_flaky: 0,
+            setup_failed: false,
+        }
+    );
+
+    assert_log!(out, r#"
+        TAP version 14
+        1..17
+        ok 1 - cat # SKIP
+        ok 2 - "ups" # SKIP
+        ok 3 - dog # SKIP
+        ok 4 - [apple] fox # SKIP
+        ok 5 - [apple] bunny # SKIP
+        ok 6 - frog # SKIP
+        ok 7 - owl # SKIP
+        ok 8 - [banana] fly # SKIP
+        ok 9 - [banana] bear # SKIP
+        ok 10 - red
+          ---
+          median: 32
+          deviation: 3
+          ...
+        not ok 11 - blue
+          ---
+          message: "sky fell down"
+          ...
+        ok 12 - [kiwi] yellow
+          ---
+          median: 64
+          deviation: 4
+          ...
+        not ok 13 - [kiwi] green
+          ---
+          message: "was poisoned"
+          ...
+        ok 14 - purple # SKIP
+        ok 15 - cyan # SKIP
+        ok 16 - [banana] orange # SKIP
+        ok 17 - [banana] pink # SKIP
+        # 0 passed; 2 failed; 13 ignored; 2 measured; 0 filtered out; finished in 0.00s
+    "#);
+}
+
+#[test]
+fn tap_output_fail_fast() {
+    // The plan covers all trials, so the ones that were not run are skipped.
+    let (c, out) = do_run(
+        args(["--format", "tap", "--fail-fast", "--test-threads", "1", "o"]),
+        tests(),
+    );
+    assert_eq!(c.num_not_run, 5);
+
+    assert_log!(out, r#"
+        TAP version 14
+        1..6
+        not ok 1 - dog
+          ---
+          message: "was not a good boy"
+          ...
+        ok 2 - [apple] fox # SKIP not run
+        ok 3 - frog # SKIP not run
+        ok 4 - owl # SKIP not run
+        ok 5 - [kiwi] yellow # SKIP not run
+        ok 6 - [banana] orange # SKIP not run
+        # 0 passed; 1 failed; 0 ignored; 0 measured; 11 filtered out; 5 not run; finished in 0.00s
+    "#);
+}
+
+#[test]
+fn report_files() {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("libtest_mimic_reports");
+    let tap = dir.join("report.tap").display().to_string();
+    let json = dir.join("report.json").display().to_string();
+    let (c, out) = do_run(
+        args([
+            "--format", "terse", "--test-threads", "1",
+            "--report", &format!("tap={tap}"),
+            "--report", &format!("json={json}"),
+            "og",
+        ]),
+        tests(),
+    );
+    assert_eq!(
+        Counts::from(&c),
+        Counts {
+            num_filtered_out: 15,
+            num_passed: 0,
+            num_failed: 1,
+            num_ignored: 1,
+            num_measured: 0,
+            num_timed_out: 0,
+            num_sharded_out: 0,
+            num_not_run: 0,
+            num_flaky: 0,
+            setup_failed: false,
+        }
+    );
+
+    assert_log!(out, "
+        running 2 tests
+        Fi
+        failures:
+
+        ---- dog ----
+        was not a good boy
+
+
+        failures:
+            dog
+
+        test result: FAILED. 0 passed; 1 failed; 1 ignored; 0 measured; 15 filtered out; \
+            finished in 0.00s
+    ");
+    assert_log!(std::fs::read_to_string(&tap).unwrap(), r#"
+        TAP version 14
+        1..2
+        not ok 1 - dog
+          ---
+          message: "was not a good boy"
+          ...
+        ok 2 - frog # SKIP
+        # 0 passed; 1 failed; 1 ignored; 0 measured; 15 filtered out; finished in 0.00s
+    "#);
+    let json = std::fs::read_to_string(&json).unwrap();
+    assert!(json.starts_with(r#"{ "type": "suite", "event": "started", "test_count": 2 }"#));
+    assert!(json.contains(r#"{ "type": "test", "name": "frog", "event": "ignored", "#));
+    assert!(json.lines().last().unwrap().starts_with(r#"{ "type": "suite", "event": "failed""#));
+}
+
+#[test]
+fn shuffle() {
+    let run_with_seed = |seed: &str, threads: &str| {
+        let (c, out) = do_run(args(["--shuffle-seed", seed, "--test-threads", threads]), tests());
+        assert_eq!(c.num_failed, 5);
+        out
+    };
+
+    // The same seed results in the same order.
+    let out = run_with_seed("42", "1");
+    assert!(out.starts_with("\nrunning 17 tests (shuffle seed: 42)\n"));
+    assert_eq!(out.lines().take(18).collect::<Vec<_>>(), {
+        let again = run_with_seed("42", "1");
+        again.lines().take(18).map(str::to_owned).collect::<Vec<_>>()
+    });
+
+    // A different seed (almost certainly) results in a different order.
+    assert_ne!(
+        out.lines().take(18).collect::<Vec<_>>(),
+        run_with_seed("7", "1").lines().take(18).collect::<Vec<_>>(),
+    );
+
+    // The thread pool receives the shuffled trials as well.
+    let out = run_with_seed("42", "4");
+    assert!(out.starts_with("\nrunning 17 tests (shuffle seed: 42)\n"));
+
+    // Also printed in other formats.
+    let (_, out) = do_run(args(["--shuffle-seed", "42", "--format", "json"]), tests());
+    assert!(out.starts_with(
+        r#"{ "type": "suite", "event": "started", "test_count": 17, "shuffle_seed": 42 }"#
+    ));
+}
+
+#[test]
+fn shard() {
+    let list = |a| {
+        let (_, out) = do_run(a, tests());
+        out.lines().map(str::to_owned).collect::<Vec<_>>()
+    };
+
+    // Shards are disjoint and together contain all trials.
+    let shards = [
+        list(args(["--list", "--shard", "1/3"])),
+        list(args(["--list", "--shard", "2/3"])),
+        list(args(["--list", "--shard", "3/3"])),
+    ];
+    assert!(shards.iter().all(|shard| !shard.is_empty()));
+    let mut combined = shards.concat();
+    combined.sort();
+    let mut all = list(args(["--list"]));
+    all.sort();
+    assert_eq!(combined, all);
+
+    // Sharded out trials are counted separately, after filtering.
+    let (c, out) = do_run(args(["--shard", "2/3", "--test-threads", "1", "o"]), tests());
+    assert_eq!(c.num_filtered_out, 11);
+    assert_eq!(c.num_passed + c.num_failed + c.num_ignored + c.num_sharded_out, 6);
+    assert!(c.num_sharded_out > 0);
+    assert!(out.contains(&format!(
+        "11 filtered out; {} sharded out; finished in",
+        c.num_sharded_out,
+    )));
+}
+
+#[test]
+fn parse_shard() {
+    let shard = args(["--shard", "2/3"]).shard.unwrap();
+    assert_eq!(shard, Shard::new(2, 3));
+    assert_eq!((shard.index(), shard.total()), (2, 3));
+}
+
+#[test]
+#[should_panic(expected = "shard index must be between 1 and 0, but is 0")]
+fn invalid_shard() {
+    Shard::new(0, 0);
+}
+
+#[test]
+fn fail_fast() {
+    let (c, out) = do_run(args(["--fail-fast", "--test-threads", "1"]), tests());
+    assert_eq!(Counts::from(&c), Counts {
+        num_filtered_out: 0,
+        num_passed: 1,
+        num_failed: 1,
+        num_ignored: 0,
+        num_measured: 0,
+        num_timed_out: 0,
+        num_sharded_out: 0,
+        num_not_run: 15,
+        num_flaky: 0,
+        setup_failed: false,
+    });
+    assert_log!(out, r#"
+        running 17 tests
+        test          cat    ... ok
+        test          "ups"  ... FAILED
+
+        failures:
+
+        ---- "ups" ----
+        failed to parse "abc"
+
+
+        failures:
+            "ups"
+
+        test result: FAILED. 1 passed; 1 failed; 0 ignored; 0 measured; 0 filtered out; 15 not run; finished in 0.00s
+    "#);
+
+    // In the thread pool, trials that are already running are finished.
+    let (c, _) = do_run(args(["--max-fail", "2", "--test-threads", "4"]), tests());
+    assert!(c.num_failed >= 2);
+    assert!(c.num_not_run > 0);
+    assert_eq!(c.num_passed + c.num_failed + c.num_ignored + c.num_not_run, 17);
+
+    let (c, out) = do_run(
+        args(["--max-fail", "3", "--format", "json", "--test-threads", "1"]),
+        tests(),
+    );
+    assert_eq!(c.num_not_run, 12);
+    let summary = out.trim_end().lines().last().unwrap();
+    assert!(summary.contains(r#""filtered_out": 0, "not_run": 12,"#));
+}
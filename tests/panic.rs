@@ -1,5 +1,5 @@
-use common::{args, check};
-use libtest_mimic::{Trial, Conclusion};
+use common::{args, check, do_run, Counts};
+use libtest_mimic::{Trial};
 
 #[macro_use]
 mod common;
@@ -15,12 +15,17 @@ fn tests() -> Vec<Trial> {
 #[test]
 fn normal() {
     check(args([]), tests, 2,
-        Conclusion {
+        Counts {
             num_filtered_out: 0,
             num_passed: 1,
             num_failed: 1,
             num_ignored: 0,
             num_measured: 0,
+            num_timed_out: 0,
+            num_sharded_out: 0,
+            num_not_run: 0,
+            num_flaky: 0,
+            setup_failed: false,
         },
         "
             test passes ... ok
@@ -30,6 +35,7 @@ fn normal() {
 
             ---- panics ----
             test panicked: uh oh
+              --> tests/panic.rs:11
 
 
             failures:
@@ -37,3 +43,105 @@ fn normal() {
         "
     );
 }
+
+fn should_panic_tests() -> Vec<Trial> {
+    vec![
+        Trial::test("panics", || panic!("uh oh")).should_panic(),
+        Trial::test("passes", || Ok(())).should_panic(),
+        Trial::test("matches", || panic!("uh oh: {}", 3)).should_panic_with("oh: 3"),
+        Trial::test("mismatches", || panic!("uh oh")).should_panic_with("whoops"),
+        Trial::test("normal", || Ok(())),
+    ]
+}
+
+#[test]
+fn should_panic() {
+    check(args([]), should_panic_tests, 5,
+        Counts {
+            num_filtered_out: 0,
+            num_passed: 3,
+            num_failed: 2,
+            num_ignored: 0,
+            num_measured: 0,
+            num_timed_out: 0,
+            num_sharded_out: 0,
+            num_not_run: 0,
+            num_flaky: 0,
+            setup_failed: false,
+        },
+        r#"
+            test panics - should panic     ... ok
+            test passes - should panic     ... FAILED
+            test matches - should panic    ... ok
+            test mismatches - should panic ... FAILED
+            test normal                    ... ok
+
+            failures:
+
+            ---- passes ----
+            test did not panic as expected
+
+            ---- mismatches ----
+            panic did not contain expected string
+                  panic message: "uh oh"
+             expected substring: "whoops"
+              --> tests/panic.rs:52
+
+
+            failures:
+                passes
+                mismatches
+        "#
+    );
+}
+
+#[test]
+fn exclude_should_panic() {
+    check(args(["--exclude-should-panic"]), should_panic_tests, 1,
+        Counts {
+            num_filtered_out: 4,
+            num_passed: 1,
+            num_failed: 0,
+            num_ignored: 0,
+            num_measured: 0,
+            num_timed_out: 0,
+            num_sharded_out: 0,
+            num_not_run: 0,
+            num_flaky: 0,
+            setup_failed: false,
+        },
+        "
+            test normal ... ok
+        "
+    );
+}
+
+#[test]
+fn panic_location_json() {
+    let (_, out) = do_run(args(["--format", "json", "--exact", "panics"]), tests());
+    assert_log!(out, r#"
+        { "type": "suite", "event": "started", "test_count": 1 }
+        { "type": "test", "event": "started", "name": "panics" }
+        { "type": "test", "name": "panics", "event": "failed", "exec_time": 0.000000000, "stdout": "Error: \"test panicked: uh oh\"\n", "file": "tests/panic.rs", "line": 11 }
+        { "type": "suite", "event": "failed", "passed": 0, "failed": 1, "ignored": 0, "measured": 0, "filtered_out": 1, "exec_time": 0.000000000 }
+    "#);
+}
+
+#[test]
+fn panic_details_are_not_stale() {
+    // The first trial catches a panic itself, the second one unwinds without
+    // calling the panic hook, so there is no location to report for it.
+    let trials = vec![
+        Trial::test("catches", || {
+            let _ = std::panic::catch_unwind(|| panic!("caught"));
+            Ok(())
+        }),
+        Trial::test("resumes", || std::panic::resume_unwind(Box::new("resumed"))),
+    ];
+    let (c, out) = do_run(args(["--format", "json", "--test-threads", "1"]), trials);
+
+    assert_eq!(c.num_failed, 1);
+    let failure = out.lines().find(|l| l.contains(r#""event": "failed""#)).unwrap();
+    assert!(failure.contains("test panicked: resumed"), "{failure}");
+    assert!(!failure.contains(r#""file""#), "{failure}");
+}
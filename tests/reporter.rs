@@ -0,0 +1,95 @@
+use std::time::Duration;
+
+use libtest_mimic::{Arguments, Conclusion, Outcome, Reporter, TestInfo, Trial, TrialResult};
+use pretty_assertions::assert_eq;
+
+
+/// Records all events as strings.
+#[derive(Default)]
+struct Recorder {
+    events: Vec<String>,
+}
+
+impl Reporter for Recorder {
+    fn run_started(&mut self, num_tests: u64, shuffle_seed: Option<u64>) {
+        self.events.push(format!("run_started {num_tests} {shuffle_seed:?}"));
+    }
+
+    fn trial_started(&mut self, info: &TestInfo) {
+        self.events.push(format!("trial_started {}", info.name()));
+    }
+
+    fn trial_finished(&mut self, info: &TestInfo, result: &TrialResult) {
+        let outcome = match &result.outcome {
+            Outcome::Passed => "passed".to_owned(),
+            Outcome::Failed(failed) => format!("failed: {}", failed.message().unwrap_or("")),
+            Outcome::Ignored(_) => "ignored".to_owned(),
+            Outcome::Measured(_) => "measured".to_owned(),
+            Outcome::TimedOut(_) => "timed out".to_owned(),
+            _ => "other".to_owned(),
+        };
+        self.events.push(format!("trial_finished {} [{}] {outcome}", info.name(), info.kind()));
+    }
+
+    fn failures(&mut self, failures: &[(TestInfo, TrialResult)]) {
+        let names = failures.iter().map(|(info, _)| info.name()).collect::<Vec<_>>();
+        self.events.push(format!("failures {}", names.join(",")));
+    }
+
+    fn summary(&mut self, conclusion: &Conclusion, _: Duration) {
+        self.events.push(format!(
+            "summary {} passed, {} failed",
+            conclusion.num_passed,
+            conclusion.num_failed,
+        ));
+    }
+
+    fn list(&mut self, trials: &[&TestInfo]) {
+        for info in trials {
+            self.events.push(format!("list {}", info.name()));
+        }
+    }
+}
+
+fn args<const N: usize>(args: [&str; N]) -> Arguments {
+    Arguments::from_iter(std::iter::once("<dummy-executable>").chain(args))
+}
+
+fn tests() -> Vec<Trial> {
+    vec![
+        Trial::test("foo", || Ok(())),
+        Trial::test("bar", || Err("nope".into())).with_kind("k"),
+        Trial::test("baz", || Ok(())).with_ignored_flag(true),
+    ]
+}
+
+#[test]
+fn events_are_reported() {
+    let mut recorder = Recorder::default();
+    let c = libtest_mimic::run_with_reporter(
+        &args(["--test-threads", "1"]),
+        tests(),
+        &mut recorder,
+    );
+
+    assert_eq!(c.num_passed, 1);
+    assert_eq!(c.num_failed, 1);
+    assert_eq!(recorder.events, [
+        "run_started 3 None",
+        "trial_started foo",
+        "trial_finished foo [] passed",
+        "trial_started bar",
+        "trial_finished bar [k] failed: nope",
+        "trial_started baz",
+        "trial_finished baz [] ignored",
+        "failures bar",
+        "summary 1 passed, 1 failed",
+    ]);
+}
+
+#[test]
+fn list() {
+    let mut recorder = Recorder::default();
+    let _ = libtest_mimic::run_with_reporter(&args(["--list", "ba"]), tests(), &mut recorder);
+    assert_eq!(recorder.events, ["list bar", "list baz"]);
+}
@@ -0,0 +1,154 @@
+use std::{
+    fs,
+    path::PathBuf,
+    sync::{atomic::{AtomicBool, Ordering}, Arc, Mutex},
+};
+
+use common::{args, do_run, fresh_tmp_path, Counts};
+use libtest_mimic::{Trial};
+
+#[macro_use]
+mod common;
+
+
+/// Returns a fresh path for a failures file.
+fn failures_path(name: &str) -> PathBuf {
+    fresh_tmp_path("libtest_mimic_failures", name)
+}
+
+/// `dog` fails unless `fixed` is set, `owl` always fails.
+fn tests(fixed: &Arc<AtomicBool>) -> Vec<Trial> {
+    let fixed = fixed.clone();
+    vec![
+        Trial::test("cat", || Ok(())),
+        Trial::test("dog", move || match fixed.load(Ordering::SeqCst) {
+            true => Ok(()),
+            false => Err("woof".into()),
+        }),
+        Trial::test("fox", || Err("ignored".into())).with_ignored_flag(true),
+        Trial::test("owl", || Err("hoot".into())).with_kind("bird"),
+    ]
+}
+
+#[test]
+fn failures_are_recorded() {
+    let path = failures_path("recorded");
+    let path_arg = path.display().to_string();
+    let fixed = Arc::new(AtomicBool::new(false));
+    let (c, _) = do_run(args(["--failures-file", &path_arg]), tests(&fixed));
+
+    assert_eq!(c.num_failed, 2);
+    assert_eq!(fs::read_to_string(&path).unwrap(), "[bird] owl\ndog\n");
+}
+
+#[test]
+fn rerun_failed() {
+    let path = failures_path("rerun");
+    let path_arg = path.display().to_string();
+    let fixed = Arc::new(AtomicBool::new(false));
+    let (c, _) = do_run(args(["--failures-file", &path_arg]), tests(&fixed));
+    assert_eq!(c.num_failed, 2);
+
+    fixed.store(true, Ordering::SeqCst);
+    let (c, _) = do_run(
+        args(["--failures-file", &path_arg, "--rerun-failed", "--test-threads", "1"]),
+        tests(&fixed),
+    );
+    assert_eq!(Counts::from(&c), Counts {
+        num_filtered_out: 2,
+        num_passed: 1,
+        num_failed: 1,
+        num_ignored: 0,
+        num_measured: 0,
+        num_timed_out: 0,
+        num_sharded_out: 0,
+        num_not_run: 0,
+        num_flaky: 0,
+        setup_failed: false,
+    });
+    assert_eq!(fs::read_to_string(&path).unwrap(), "[bird] owl\n");
+
+    // Trials that do not run keep their state.
+    let (c, _) = do_run(args(["--failures-file", &path_arg, "cat"]), tests(&fixed));
+    assert_eq!(c.num_passed, 1);
+    assert_eq!(fs::read_to_string(&path).unwrap(), "[bird] owl\n");
+}
+
+#[test]
+fn rerun_failed_list() {
+    let path = failures_path("list");
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(&path, "[bird] owl\ndog\n").unwrap();
+    let path_arg = path.display().to_string();
+    let fixed = Arc::new(AtomicBool::new(false));
+    let (_, out) = do_run(
+        args(["--failures-file", &path_arg, "--rerun-failed", "--list"]),
+        tests(&fixed),
+    );
+
+    assert_log!(out, "
+        dog: test
+        [bird] owl: test
+    ");
+}
+
+#[test]
+fn rerun_failed_without_failures() {
+    let path = failures_path("none");
+    let path_arg = path.display().to_string();
+    let fixed = Arc::new(AtomicBool::new(false));
+    let (c, _) = do_run(args(["--failures-file", &path_arg, "--rerun-failed"]), tests(&fixed));
+
+    assert_eq!(c.num_filtered_out, 4);
+    assert!(!c.has_failed());
+}
+
+#[test]
+fn failed_first() {
+    let path = failures_path("failed_first");
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(&path, "[bird] owl\ndog\n").unwrap();
+    let path_arg = path.display().to_string();
+
+    let order = Arc::new(Mutex::new(Vec::new()));
+    let trial = |name: &'static str| {
+        let order = order.clone();
+        Trial::test(name, move || {
+            order.lock().unwrap().push(name);
+            Ok(())
+        })
+    };
+    let trials = vec![
+        trial("ant"),
+        trial("dog"),
+        trial("cat"),
+        trial("owl").with_kind("bird"),
+        trial("owl"),
+    ];
+    let (c, _) = do_run(
+        args(["--failures-file", &path_arg, "--failed-first", "--test-threads", "1"]),
+        trials,
+    );
+
+    assert_eq!(c.num_passed, 5);
+    assert_eq!(*order.lock().unwrap(), ["dog", "owl", "ant", "cat", "owl"]);
+    // All of them passed now.
+    assert_eq!(fs::read_to_string(&path).unwrap(), "");
+}
+
+#[test]
+fn unwritable_failures_file() {
+    // The parent of the failures file is a file, so it cannot be created.
+    let parent = failures_path("not_a_dir");
+    fs::create_dir_all(parent.parent().unwrap()).unwrap();
+    fs::write(&parent, "").unwrap();
+    let path_arg = parent.join("failures").display().to_string();
+    let fixed = Arc::new(AtomicBool::new(false));
+    let (c, out) = do_run(
+        args(["--failures-file", &path_arg, "--test-threads", "1"]),
+        tests(&fixed),
+    );
+
+    assert_eq!(c.num_failed, 2);
+    assert!(out.contains("test result: FAILED. 1 passed; 2 failed;"), "{out}");
+}
@@ -0,0 +1,132 @@
+use std::{
+    sync::Arc,
+    thread,
+    time::{Duration, Instant},
+};
+
+use common::{args, do_run_with_options, Concurrency};
+use libtest_mimic::{RunOptions, Trial};
+
+#[macro_use]
+mod common;
+
+
+#[test]
+fn same_resource_never_concurrent() {
+    let db = Arc::new(Concurrency::default());
+    let trials = (0..4)
+        .map(|i| db.trial(&format!("db{i}"), 1).with_resource("db"))
+        .collect();
+    let (c, _) = do_run_with_options(args(["--test-threads", "4"]), trials, RunOptions::new());
+
+    assert_eq!(c.num_passed, 4);
+    assert_eq!(db.max(), 1);
+}
+
+#[test]
+fn serial_group() {
+    let group = Arc::new(Concurrency::default());
+    let trials = (0..4)
+        .map(|i| group.trial(&format!("g{i}"), 1).serial_group("fixtures"))
+        .collect();
+    let (c, _) = do_run_with_options(args(["--test-threads", "4"]), trials, RunOptions::new());
+
+    assert_eq!(c.num_passed, 4);
+    assert_eq!(group.max(), 1);
+}
+
+#[test]
+fn resource_limit() {
+    let gpu = Arc::new(Concurrency::default());
+    let trials = (0..6)
+        .map(|i| gpu.trial(&format!("gpu{i}"), 1).with_resource("gpu-sim"))
+        .collect();
+    let options = RunOptions::new().with_resource_limit("gpu-sim", 2);
+    let (c, _) = do_run_with_options(args(["--test-threads", "4"]), trials, options);
+
+    assert_eq!(c.num_passed, 6);
+    assert_eq!(gpu.max(), 2);
+}
+
+#[test]
+fn multiple_resources() {
+    let a = Arc::new(Concurrency::default());
+    let b = Arc::new(Concurrency::default());
+    let trials = vec![
+        a.trial("a1", 1).with_resource("a"),
+        a.trial("a2", 1).with_resource("a"),
+        b.trial("b1", 1).with_resource("b"),
+        // Uses both, so it runs neither with `a*` nor with `b*`.
+        Trial::test("ab", {
+            let (a, b) = (a.clone(), b.clone());
+            move || {
+                a.start(1);
+                b.start(1);
+                thread::sleep(Duration::from_millis(50));
+                a.finish(1);
+                b.finish(1);
+                Ok(())
+            }
+        })
+        .with_resource("a")
+        .with_resource("b"),
+    ];
+    let (c, _) = do_run_with_options(args(["--test-threads", "4"]), trials, RunOptions::new());
+
+    assert_eq!(c.num_passed, 4);
+    assert_eq!(a.max(), 1);
+    assert_eq!(b.max(), 1);
+}
+
+#[test]
+fn other_trials_still_parallel() {
+    let serial = Arc::new(Concurrency::default());
+    let free = Arc::new(Concurrency::default());
+    let mut trials = vec![];
+    for i in 0..4 {
+        trials.push(serial.trial(&format!("serial{i}"), 1).with_resource("port-8080"));
+        trials.push(free.trial(&format!("free{i}"), 1));
+    }
+
+    let before = Instant::now();
+    let (c, _) = do_run_with_options(args(["--test-threads", "4"]), trials, RunOptions::new());
+
+    assert_eq!(c.num_passed, 8);
+    assert_eq!(serial.max(), 1);
+    assert!(free.max() > 1, "unrelated trials did not run in parallel");
+    // Serially, this would take 400ms.
+    assert!(before.elapsed() < Duration::from_millis(390));
+}
+
+#[test]
+fn timed_out_trial_releases_resources() {
+    // The runner of the timed out trial keeps running, but the resource is
+    // released anyway, so the next trial runs at the same time.
+    let db = Arc::new(Concurrency::default());
+    let hangs = {
+        let db = db.clone();
+        Trial::test("hangs", move || {
+            db.start(1);
+            thread::sleep(Duration::from_millis(300));
+            db.finish(1);
+            Ok(())
+        })
+    };
+    let trials = vec![
+        hangs.with_resource("db").with_timeout(Duration::from_millis(50)),
+        db.trial("next", 1).with_resource("db"),
+    ];
+    let (c, _) = do_run_with_options(args(["--test-threads", "2"]), trials, RunOptions::new());
+
+    assert_eq!((c.num_timed_out, c.num_passed), (1, 1));
+    assert_eq!(db.max(), 2);
+}
+
+#[test]
+fn accessors() {
+    let trial = Trial::test("x", || Ok(()))
+        .with_resource("db")
+        .serial_group("db")
+        .with_resource("net");
+    assert_eq!(trial.resources(), ["db", "net"]);
+}
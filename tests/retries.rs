@@ -0,0 +1,91 @@
+use std::sync::{
+    atomic::{AtomicU32, Ordering},
+    Arc,
+};
+
+use common::{args, check, do_run, Counts};
+use libtest_mimic::{Failed, Trial};
+use pretty_assertions::assert_eq;
+
+#[macro_use]
+mod common;
+
+
+/// Returns a runner that fails the first `num_failures` times it is called.
+fn fails_first(num_failures: u32) -> impl Fn() -> Result<(), Failed> + Send + Sync {
+    let calls = Arc::new(AtomicU32::new(0));
+    move || {
+        let call = calls.fetch_add(1, Ordering::SeqCst);
+        if call < num_failures {
+            Err(format!("failed attempt {}", call + 1).into())
+        } else {
+            Ok(())
+        }
+    }
+}
+
+fn tests() -> Vec<Trial> {
+    vec![
+        Trial::test_repeatable("stable", fails_first(0)).with_retries(2),
+        Trial::test_repeatable("flaky", fails_first(2)).with_retries(2),
+        Trial::test_repeatable("broken", fails_first(3)).with_retries(2),
+        Trial::test("once", || Err("nope".into())).with_retries(2),
+    ]
+}
+
+#[test]
+fn per_trial_retries() {
+    check(args([]), tests, 4,
+        Counts {
+            num_filtered_out: 0,
+            num_passed: 1,
+            num_failed: 2,
+            num_ignored: 0,
+            num_measured: 0,
+            num_timed_out: 0,
+            num_sharded_out: 0,
+            num_not_run: 0,
+            num_flaky: 1,
+            setup_failed: false,
+        },
+        "
+            test stable ... ok
+            test flaky  ... FLAKY
+            test broken ... FAILED
+            test once   ... FAILED
+
+            failures:
+
+            ---- broken ----
+            failed attempt 3
+
+            ---- once ----
+            nope
+
+
+            failures:
+                broken
+                once
+        "
+    );
+}
+
+#[test]
+fn global_retries() {
+    let tests = || vec![
+        Trial::test_repeatable("flaky", fails_first(1)),
+        Trial::test_repeatable("overridden", fails_first(1)).with_retries(0),
+    ];
+
+    let (c, out) = do_run(args(["--retries", "1", "--format", "json", "--test-threads", "1"]), tests());
+    assert_eq!(c.num_flaky, 1);
+    assert_eq!(c.num_failed, 1);
+    assert_log!(out, r#"
+        { "type": "suite", "event": "started", "test_count": 2 }
+        { "type": "test", "event": "started", "name": "flaky" }
+        { "type": "test", "name": "flaky", "event": "flaky", "exec_time": 0.000000000, "attempts": 2 }
+        { "type": "test", "event": "started", "name": "overridden" }
+        { "type": "test", "name": "overridden", "event": "failed", "exec_time": 0.000000000, "stdout": "Error: \"failed attempt 1\"\n" }
+        { "type": "suite", "event": "failed", "passed": 0, "failed": 1, "flaky": 1, "ignored": 0, "measured": 0, "filtered_out": 0, "exec_time": 0.000000000 }
+    "#);
+}
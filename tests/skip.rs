@@ -0,0 +1,83 @@
+use common::{args, check, do_run, Counts};
+use libtest_mimic::{Failed, Trial};
+
+#[macro_use]
+mod common;
+
+
+fn tests() -> Vec<Trial> {
+    vec![
+        Trial::test("runs", || Ok(())),
+        Trial::test("skips", || Err(Failed::skip("tool not found"))),
+        Trial::bench("skips_bench", |_| Err(Failed::skip("no hardware"))),
+        Trial::test("ignored", || Ok(())).with_ignored_flag(true),
+        Trial::test("needs_network", || Ok(())).with_ignore_reason("needs network"),
+    ]
+}
+
+#[test]
+fn normal() {
+    check(args([]), tests, 5,
+        Counts {
+            num_filtered_out: 0,
+            num_passed: 1,
+            num_failed: 0,
+            num_ignored: 4,
+            num_measured: 0,
+            num_timed_out: 0,
+            num_sharded_out: 0,
+            num_not_run: 0,
+            num_flaky: 0,
+            setup_failed: false,
+        },
+        "
+            test runs          ... ok
+            test skips         ... ignored, tool not found
+            test skips_bench   ... ignored, no hardware
+            test ignored       ... ignored
+            test needs_network ... ignored, needs network
+        "
+    );
+}
+
+#[test]
+fn json() {
+    let (_, out) = do_run(args(["--format", "json", "--test-threads", "1", "skips"]), tests());
+    assert_log!(out, r#"
+        { "type": "suite", "event": "started", "test_count": 2 }
+        { "type": "test", "event": "started", "name": "skips" }
+        { "type": "test", "name": "skips", "event": "ignored", "exec_time": 0.000000000, "message": "tool not found" }
+        { "type": "test", "event": "started", "name": "skips_bench" }
+        { "type": "test", "name": "skips_bench", "event": "ignored", "exec_time": 0.000000000, "message": "no hardware" }
+        { "type": "suite", "event": "ok", "passed": 0, "failed": 0, "ignored": 2, "measured": 0, "filtered_out": 3, "exec_time": 0.000000000 }
+    "#);
+}
+
+#[test]
+fn ignore_reason_json() {
+    let (_, out) = do_run(args(["--format", "json", "needs_network"]), tests());
+    assert_log!(out, r#"
+        { "type": "suite", "event": "started", "test_count": 1 }
+        { "type": "test", "event": "started", "name": "needs_network" }
+        { "type": "test", "name": "needs_network", "event": "ignored", "exec_time": 0.000000000, "message": "needs network" }
+        { "type": "suite", "event": "ok", "passed": 0, "failed": 0, "ignored": 1, "measured": 0, "filtered_out": 4, "exec_time": 0.000000000 }
+    "#);
+}
+
+#[test]
+fn list() {
+    let (_, out) = do_run(args(["--list"]), tests());
+    assert_log!(out, "
+        runs: test
+        skips: test
+        skips_bench: bench
+        ignored: test
+        needs_network: test (ignored, needs network)
+    ");
+}
+
+#[test]
+fn run_ignored() {
+    let (c, _) = do_run(args(["--ignored", "needs_network"]), tests());
+    assert_eq!(c.num_passed, 1);
+}
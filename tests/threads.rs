@@ -6,8 +6,7 @@ use libtest_mimic::{Trial, Arguments};
 fn check_test_on_main_thread() {
     let outer_thread = std::thread::current().id();
 
-    let mut args = Arguments::default();
-    args.test_threads = Some(1);
+    let args = Arguments { test_threads: Some(1), ..Arguments::default() };
     let conclusion = libtest_mimic::run(&args, vec![Trial::test("check", move || {
         assert_eq!(outer_thread, std::thread::current().id());
         Ok(())
@@ -18,8 +17,7 @@ fn check_test_on_main_thread() {
 
 #[test]
 fn all_tests_run_on_single_thread() {
-    let mut args = Arguments::default();
-    args.test_threads = Some(1);
+    let args = Arguments { test_threads: Some(1), ..Arguments::default() };
     let trials = vec![
         Trial::test("a", move || Ok(())),
         Trial::test("b", move || Ok(())),
@@ -31,8 +29,7 @@ fn all_tests_run_on_single_thread() {
 
 #[test]
 fn all_tests_run_on_two_threads() {
-    let mut args = Arguments::default();
-    args.test_threads = Some(2);
+    let args = Arguments { test_threads: Some(2), ..Arguments::default() };
     let trials = vec![
         Trial::test("a", move || Ok(())),
         Trial::test("b", move || Ok(())),
@@ -49,8 +46,7 @@ fn all_tests_run_on_two_threads() {
 // jankiness I think.
 #[test]
 fn multi_threads_are_used() {
-    let mut args = Arguments::default();
-    args.test_threads = Some(4);
+    let args = Arguments { test_threads: Some(4), ..Arguments::default() };
     let trials = vec![
         Trial::test("a", move || { std::thread::sleep(Duration::from_secs(1)); Ok(()) }),
         Trial::test("b", move || { std::thread::sleep(Duration::from_secs(1)); Ok(()) }),
@@ -0,0 +1,150 @@
+use std::time::Duration;
+
+use common::{args, check, do_run, Counts};
+use libtest_mimic::{Trial, TimeThreshold};
+use pretty_assertions::assert_eq;
+
+#[macro_use]
+mod common;
+
+
+fn sleep_for(secs: u64) -> Result<(), libtest_mimic::Failed> {
+    std::thread::sleep(Duration::from_secs(secs));
+    Ok(())
+}
+
+fn tests() -> Vec<Trial> {
+    vec![
+        Trial::test("fast", || Ok(())),
+        Trial::test("stuck", || sleep_for(60)).with_timeout(Duration::from_millis(50)),
+        Trial::test("panics", || panic!("oh no")).with_timeout(Duration::from_secs(60)),
+    ]
+}
+
+#[test]
+fn per_trial_timeout() {
+    check(args([]), tests, 3,
+        Counts {
+            num_filtered_out: 0,
+            num_passed: 1,
+            num_failed: 1,
+            num_ignored: 0,
+            num_measured: 0,
+            num_timed_out: 1,
+            num_sharded_out: 0,
+            num_not_run: 0,
+            num_flaky: 0,
+            setup_failed: false,
+        },
+        "
+            test fast   ... ok
+            test stuck  ... TIMEOUT
+            test panics ... FAILED
+
+            failures:
+
+            ---- stuck ----
+            test did not finish within 50ms
+
+            ---- panics ----
+            test panicked: oh no
+              --> tests/timeout.rs:20
+
+
+            failures:
+                stuck
+                panics
+        "
+    );
+}
+
+#[test]
+fn global_timeout() {
+    let tests = || vec![
+        Trial::test("fast", || Ok(())),
+        Trial::test("stuck", || sleep_for(60)),
+        Trial::test("overridden", || sleep_for(1)).with_timeout(Duration::from_secs(60)),
+    ];
+
+    check(args(["--timeout", "0.05"]), tests, 3,
+        Counts {
+            num_filtered_out: 0,
+            num_passed: 2,
+            num_failed: 0,
+            num_ignored: 0,
+            num_measured: 0,
+            num_timed_out: 1,
+            num_sharded_out: 0,
+            num_not_run: 0,
+            num_flaky: 0,
+            setup_failed: false,
+        },
+        "
+            test fast       ... ok
+            test stuck      ... TIMEOUT
+            test overridden ... ok
+
+            failures:
+
+            ---- stuck ----
+            test did not finish within 50ms
+
+
+            failures:
+                stuck
+        "
+    );
+}
+
+#[test]
+fn ensure_time() {
+    fn sleep_ms(ms: u64) -> Result<(), libtest_mimic::Failed> {
+        std::thread::sleep(Duration::from_millis(ms));
+        Ok(())
+    }
+    let threshold = |warn, critical| {
+        TimeThreshold::new(Duration::from_millis(warn), Duration::from_millis(critical))
+    };
+    let tests = || vec![
+        Trial::test("fast", || Ok(())),
+        Trial::test("slow", || sleep_ms(30)),
+        Trial::test("slow_kind", || sleep_ms(30)).with_kind("quick"),
+        Trial::test("slow_overridden", || sleep_ms(30))
+            .with_kind("quick")
+            .with_time_threshold(threshold(1000, 2000)),
+        Trial::test("slow_failing", || Err("nope".into())).with_kind("quick"),
+    ];
+
+    let (c, out) = do_run(
+        args(["--ensure-time", "--time-threshold", "quick=0.001,0.01", "--test-threads", "1"]),
+        tests(),
+    );
+    assert_eq!(Counts::from(&c), Counts {
+        num_filtered_out: 0,
+        num_passed: 3,
+        num_failed: 2,
+        num_ignored: 0,
+        num_measured: 0,
+        num_timed_out: 0,
+        num_sharded_out: 0,
+        num_not_run: 0,
+        num_flaky: 0,
+        setup_failed: false,
+    });
+    assert!(out.contains("test [quick] slow_kind       ... FAILED <0."));
+    assert!(out.contains("exceeding the critical time limit of 0.010s"));
+    assert!(out.contains("---- slow_failing ----\nnope\n"));
+
+    // Without `--ensure-time`, the thresholds only affect printing.
+    let (c, out) = do_run(args(["--time-threshold", "quick=0.001,0.01"]), tests());
+    assert_eq!(c.num_failed, 1);
+    assert!(out.contains("test [quick] slow_kind       ... ok\n"));
+}
+
+#[test]
+fn json_output() {
+    let (_, out) = do_run(args(["--format", "json", "--exact", "stuck"]), tests());
+    let expected = r#""stdout": "Error: \"test did not finish within 50ms\"\n" }"#;
+    assert!(out.contains(r#""name": "stuck", "event": "timeout""#), "{out}");
+    assert!(out.contains(expected), "{out}");
+}
@@ -0,0 +1,121 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+use common::{args, do_run, fresh_tmp_path};
+use libtest_mimic::Trial;
+
+#[macro_use]
+mod common;
+
+
+/// Returns a fresh path for a history file.
+fn history_path(name: &str) -> PathBuf {
+    fresh_tmp_path("libtest_mimic_timing_history", name)
+}
+
+/// Returns the names in the history file, in the order of the file.
+fn names_in(path: &Path) -> Vec<String> {
+    fs::read_to_string(path).unwrap()
+        .lines()
+        .map(|line| line.split_once('\t').unwrap().1.to_owned())
+        .collect()
+}
+
+#[test]
+fn history_is_written() {
+    let path = history_path("written");
+    let trials = vec![
+        Trial::test("cat", || Ok(())),
+        Trial::test("dog", || Err("woof".into())),
+        Trial::test("fox", || Ok(())).with_ignored_flag(true),
+        Trial::test("owl", || Ok(())).with_kind("bird"),
+    ];
+    let path_arg = path.display().to_string();
+    let (c, _) = do_run(args(["--timing-history", &path_arg]), trials);
+    assert_eq!(c.num_failed, 1);
+
+    // Ignored trials did not run, so we know nothing about them.
+    assert_eq!(names_in(&path), ["[bird] owl", "cat", "dog"]);
+}
+
+#[test]
+fn history_is_merged() {
+    let path = history_path("merged");
+    let trials = || vec![
+        Trial::test("cat", || Ok(())),
+        Trial::test("dog", || Ok(())),
+        Trial::test("fox", || Ok(())),
+    ];
+    let path_arg = path.display().to_string();
+    let (c, _) = do_run(args(["--timing-history", &path_arg, "cat"]), trials());
+    assert_eq!(c.num_passed, 1);
+    assert_eq!(names_in(&path), ["cat"]);
+    let (c, _) = do_run(args(["--timing-history", &path_arg, "--skip", "cat"]), trials());
+    assert_eq!(c.num_passed, 2);
+    assert_eq!(names_in(&path), ["cat", "dog", "fox"]);
+}
+
+#[test]
+fn malformed_history_is_ignored() {
+    let path = history_path("malformed");
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(&path, "garbage\n12\tcat\nx\tdog\n").unwrap();
+    let path_arg = path.display().to_string();
+    let (c, _) = do_run(args(["--timing-history", &path_arg]), vec![
+        Trial::test("fox", || Ok(())),
+    ]);
+
+    assert_eq!(c.num_passed, 1);
+    assert_eq!(names_in(&path), ["cat", "fox"]);
+}
+
+#[test]
+fn longest_first() {
+    let path = history_path("longest_first");
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(&path, "1000\tshort\n9000\tlong\n5000\tmedium\n2000\tother\n").unwrap();
+
+    // With a weight of 2 on 2 threads, the trials run one after another, so
+    // the order in which they start is deterministic.
+    let started = Arc::new(Mutex::new(Vec::new()));
+    let trial = |name: &'static str| {
+        let started = started.clone();
+        Trial::test(name, move || {
+            started.lock().unwrap().push(name);
+            thread::sleep(Duration::from_millis(1));
+            Ok(())
+        })
+        .with_weight(2)
+    };
+    let trials = vec![trial("short"), trial("unknown"), trial("medium"), trial("long")];
+    let path_arg = path.display().to_string();
+    let (c, _) = do_run(
+        args(["--test-threads", "2", "--timing-history", &path_arg, "--longest-first"]),
+        trials,
+    );
+
+    assert_eq!(c.num_passed, 4);
+    // The unknown trial is assumed to take the average of the others (5000).
+    assert_eq!(*started.lock().unwrap(), ["long", "unknown", "medium", "short"]);
+}
+
+#[test]
+fn unwritable_history() {
+    // The parent of the history file is a file, so it cannot be created.
+    let parent = history_path("not_a_dir");
+    fs::create_dir_all(parent.parent().unwrap()).unwrap();
+    fs::write(&parent, "").unwrap();
+    let path_arg = parent.join("history").display().to_string();
+    let (c, out) = do_run(
+        args(["--timing-history", &path_arg, "--test-threads", "1"]),
+        vec![Trial::test("cat", || Ok(()))],
+    );
+
+    assert_eq!(c.num_passed, 1);
+    assert!(out.contains("test result: ok. 1 passed;"), "{out}");
+}
@@ -0,0 +1,93 @@
+use std::{sync::Arc, thread, time::Duration};
+
+use common::{args, do_run, Concurrency};
+use libtest_mimic::{KindThreadsSetting, Trial};
+
+#[macro_use]
+mod common;
+
+
+#[test]
+fn weights_fit_into_threads() {
+    let usage = Arc::new(Concurrency::default());
+    let trials = vec![
+        usage.trial("a", 2),
+        usage.trial("b", 1),
+        usage.trial("c", 3),
+        usage.trial("d", 2),
+        usage.trial("e", 1),
+        usage.trial("f", 1),
+    ];
+    let (c, _) = do_run(args(["--test-threads", "4"]), trials);
+
+    assert_eq!(c.num_passed, 6);
+    assert!(usage.max() <= 4, "{} threads used", usage.max());
+    assert!(usage.max() > 2, "trials did not run in parallel");
+}
+
+#[test]
+fn too_heavy_trial_runs_alone() {
+    let light = Arc::new(Concurrency::default());
+    let heavy = {
+        let light = light.clone();
+        Trial::test("heavy", move || {
+            let others = light.running();
+            thread::sleep(Duration::from_millis(50));
+            assert_eq!(others + light.running(), 0);
+            Ok(())
+        })
+        .with_weight(10)
+    };
+    let trials = vec![
+        light.trial("a", 1),
+        heavy,
+        light.trial("b", 1),
+        light.trial("c", 1),
+    ];
+    let (c, out) = do_run(args(["--test-threads", "2"]), trials);
+
+    assert_eq!(c.num_passed, 4, "{out}");
+}
+
+#[test]
+fn kind_threads() {
+    let e2e = Arc::new(Concurrency::default());
+    let unit = Arc::new(Concurrency::default());
+    let mut trials = vec![];
+    for i in 0..4 {
+        trials.push(e2e.trial(&format!("e2e{i}"), 1).with_kind("e2e"));
+        trials.push(unit.trial(&format!("unit{i}"), 1).with_kind("unit"));
+    }
+    let (c, _) = do_run(args(["--test-threads", "4", "--kind-threads", "e2e=2"]), trials);
+
+    assert_eq!(c.num_passed, 8);
+    assert_eq!(e2e.max(), 2);
+    assert!(unit.max() > 1, "unit trials did not run in parallel");
+}
+
+#[test]
+fn kind_threads_with_weight() {
+    let e2e = Arc::new(Concurrency::default());
+    let trials = (0..4)
+        .map(|i| e2e.trial(&format!("e2e{i}"), 2).with_kind("e2e"))
+        .collect();
+    let (c, _) = do_run(args(["--test-threads", "8", "--kind-threads", "e2e=4"]), trials);
+
+    assert_eq!(c.num_passed, 4);
+    assert_eq!(e2e.max(), 4);
+}
+
+#[test]
+fn parse_kind_threads() {
+    let args = args(["--kind-threads", "e2e=2", "--kind-threads", "slow=1"]);
+    assert_eq!(args.kind_threads, [
+        KindThreadsSetting { kind: "e2e".into(), threads: 2 },
+        KindThreadsSetting { kind: "slow".into(), threads: 1 },
+    ]);
+}
+
+#[test]
+fn accessors() {
+    assert_eq!(Trial::test("x", || Ok(())).weight(), 1);
+    assert_eq!(Trial::test("x", || Ok(())).with_weight(3).weight(), 3);
+}